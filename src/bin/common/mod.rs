@@ -0,0 +1,147 @@
+//! What the UCI and the XBoard engine share: reading commands, searching
+//! on a thread of its own while more commands come in, and writing lines
+//! to the GUI from either thread. The protocols themselves are up to the
+//! engines.
+
+use std::io::{self, BufRead, Write};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+
+use ellmar_chess::Searcher;
+
+/// A protocol to talk to a GUI in, one command per line.
+pub trait Protocol {
+    /// Carry out a command and return false if the engine should quit.
+    fn handle(&mut self, line: &str) -> bool;
+
+    /// Stop thinking before the engine quits.
+    fn quit(&mut self);
+}
+
+/// Carry out the commands read from standard input until the engine should quit.
+pub fn serve(mut engine: impl Protocol) {
+    for line in io::stdin().lock().lines() {
+        match line {
+            Ok(line) if engine.handle(&line) => {},
+            _ => break
+        }
+    }
+    engine.quit();
+}
+
+/// Where to write the answers to, shared with the searching thread.
+pub struct Output<W>(Arc<Mutex<W>>);
+
+impl<W: Write> Output<W> {
+    pub fn new(out: W) -> Output<W> {
+        Output(Arc::new(Mutex::new(out)))
+    }
+
+    /// Write a line to the GUI.
+    pub fn send(&self, line: &str) {
+        let mut out = self.0.lock().expect("no thread panics while writing");
+        // there is nobody left to tell if the GUI has gone away
+        let _ = writeln!(out, "{}", line).and_then(|_| out.flush());
+    }
+}
+
+impl<W> Clone for Output<W> {
+    fn clone(&self) -> Self {
+        Output(Arc::clone(&self.0))
+    }
+}
+
+/// Searches on a thread of its own, handing the searcher back together
+/// with what the search found, of type `T`, when it is done.
+pub struct Thinker<T> {
+    /// The searcher, None while it is searching on its own thread.
+    searcher: Option<Searcher>,
+    /// The thread searching.
+    search: Option<JoinHandle<(Searcher, T)>>,
+    /// Stops the search of `searcher`.
+    stop: Arc<AtomicBool>
+}
+
+impl<T: Send + 'static> Default for Thinker<T> {
+    fn default() -> Self {
+        Thinker::new()
+    }
+}
+
+impl<T: Send + 'static> Thinker<T> {
+    pub fn new() -> Thinker<T> {
+        let searcher = Searcher::new();
+        Thinker {stop: searcher.stop_flag(), searcher: Some(searcher), search: None}
+    }
+
+    /// Stop the running search, if any, throwing away what it found, and
+    /// return the searcher to change it.
+    pub fn searcher(&mut self) -> &mut Searcher {
+        self.stop();
+        self.searcher.as_mut().expect("no search is running")
+    }
+
+    /// Stop the running search, if any, and call `think` with the searcher
+    /// and its stop flag on another thread, until it returns.
+    pub fn start(&mut self, think: impl FnOnce(&mut Searcher, &AtomicBool) -> T + Send + 'static) {
+        self.stop();
+        let mut searcher = self.searcher.take().expect("no other search is running");
+        let stop = Arc::clone(&self.stop);
+        stop.store(false, Ordering::Relaxed);
+        self.search = Some(thread::spawn(move || {
+            let found = think(&mut searcher, &stop);
+            (searcher, found)
+        }));
+    }
+
+    /// Stop the running search, if any, and return what it found.
+    pub fn stop(&mut self) -> Option<T> {
+        let search = self.search.take()?;
+        self.stop.store(true, Ordering::Relaxed);
+        let (searcher, found) = search.join().expect("the search does not panic");
+        self.searcher = Some(searcher);
+        Some(found)
+    }
+}
+
+#[cfg(test)]
+impl Output<Vec<u8>> {
+    /// Return the lines written so far and forget them.
+    pub fn lines(&self) -> Vec<String> {
+        let mut out = self.0.lock().unwrap();
+        let lines = String::from_utf8(out.clone()).unwrap().lines().map(str::to_string).collect();
+        out.clear();
+        lines
+    }
+}
+
+#[cfg(test)]
+impl<T> Thinker<T> {
+    /// Wait for the running search, if any, to finish on its own.
+    pub fn wait(&self) {
+        while self.search.as_ref().is_some_and(|search| !search.is_finished()) {
+            thread::sleep(std::time::Duration::from_millis(1));
+        }
+    }
+}
+
+/// Helpers for the tests of the engines.
+#[cfg(test)]
+pub mod testing {
+    use super::{Output, Protocol};
+
+    /// Return the engine `new` makes writing into memory, and that memory.
+    pub fn engine<P>(new: impl FnOnce(Output<Vec<u8>>) -> P) -> (P, Output<Vec<u8>>) {
+        let out = Output::new(vec!());
+        (new(out.clone()), out)
+    }
+
+    /// Carry out commands that do not make the engine think and return what it wrote.
+    pub fn run(engine: &mut impl Protocol, out: &Output<Vec<u8>>, commands: &[&str]) -> Vec<String> {
+        for command in commands {
+            assert!(engine.handle(command));
+        }
+        out.lines()
+    }
+}
@@ -0,0 +1,276 @@
+//! A chess engine speaking the Universal Chess Interface on standard input
+//! and output, to be used from GUIs such as Cute Chess or Arena.
+
+use std::io::{self, Write};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use ellmar_chess::{Color, DefaultEvaluator, Game, Limit, Score, SearchResult, Weights, MAX_HASH_MEGABYTES};
+
+use common::{Output, Protocol, Thinker};
+
+mod common;
+
+fn main() {
+    common::serve(Engine::new(Output::new(io::stdout())));
+}
+
+/// The state of the engine between the commands of the GUI.
+struct Engine<W> {
+    /// The position to search from, with the moves that led to it.
+    game: Game,
+    /// Searches on its own thread, which writes the best move when it is done.
+    thinker: Thinker<()>,
+    out: Output<W>
+}
+
+impl<W: Write + Send + 'static> Protocol for Engine<W> {
+    fn handle(&mut self, line: &str) -> bool {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.split_first() {
+            Some((&"uci", _)) => {
+                self.out.send(&format!("id name {} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")));
+                self.out.send("id author the ellmar-chess developers");
+                self.out.send(&format!("option name Hash type spin default 16 min 1 max {}", MAX_HASH_MEGABYTES));
+                self.out.send("option name Clear Hash type button");
+                self.out.send("option name Weights type string default <empty>");
+                self.out.send("uciok");
+            },
+            Some((&"isready", _)) => self.out.send("readyok"),
+            Some((&"setoption", args)) => self.set_option(args),
+            Some((&"ucinewgame", _)) => {
+                self.thinker.searcher().clear_hash();
+                self.game = Game::new();
+            },
+            Some((&"position", args)) => self.set_position(args),
+            Some((&"go", args)) => self.go(args),
+            Some((&"stop", _)) => self.stop_search(),
+            Some((&"quit", _)) => return false,
+            // unknown commands are to be ignored
+            _ => {}
+        }
+        true
+    }
+
+    fn quit(&mut self) {
+        self.stop_search();
+    }
+}
+
+impl<W: Write + Send + 'static> Engine<W> {
+    fn new(out: Output<W>) -> Engine<W> {
+        Engine {game: Game::new(), thinker: Thinker::new(), out}
+    }
+
+    /// Handle `setoption name <name> value <value>`.
+    fn set_option(&mut self, args: &[&str]) {
+        let value_at = args.iter().position(|&arg| arg == "value").unwrap_or(args.len());
+        let name = args.get(1..value_at).unwrap_or(&[]).join(" ");
+        let value = args.get(value_at + 1..).unwrap_or(&[]).join(" ");
+        if name.eq_ignore_ascii_case("hash") {
+            match value.parse::<usize>() {
+                Ok(megabytes) if (1..=MAX_HASH_MEGABYTES).contains(&megabytes) => self.thinker.searcher().set_hash_size(megabytes),
+                _ => self.out.send(&format!("info string invalid hash size {}", value))
+            }
+        }
+        else if name.eq_ignore_ascii_case("clear hash") {
+            self.thinker.searcher().clear_hash();
+        }
+        else if name.eq_ignore_ascii_case("weights") {
+            let weights = match value.as_str() {
+                "" | "<empty>" => Ok(Weights::default()),
+                path => Weights::load(path)
+            };
+            match weights {
+                Ok(weights) => self.thinker.searcher().set_evaluator(DefaultEvaluator::with_weights(weights)),
+                Err(error) => self.out.send(&format!("info string cannot use weights from {}: {}", value, error))
+            }
+        }
+        else {
+            self.out.send(&format!("info string unknown option {}", name));
+        }
+    }
+
+    /// Handle `position startpos [moves ...]` and `position fen <fen> [moves ...]`.
+    fn set_position(&mut self, args: &[&str]) {
+        let moves_at = args.iter().position(|&arg| arg == "moves").unwrap_or(args.len());
+        let game = match args.first() {
+            Some(&"startpos") => Ok(Game::new()),
+            Some(&"fen") => {
+                // EPD style positions leave out the clocks
+                let mut fen = args[1..moves_at].to_vec();
+                if fen.len() == 4 {
+                    fen.extend(["0", "1"]);
+                }
+                Game::from_fen(&fen.join(" ")).map_err(|error| error.to_string())
+            },
+            _ => Err("expected startpos or fen".to_string())
+        };
+        let mut game = match game {
+            Ok(game) => game,
+            Err(error) => return self.out.send(&format!("info string invalid position: {}", error))
+        };
+        for &uci in args.get(moves_at + 1..).unwrap_or(&[]) {
+            let played = game.parse_uci(uci).map_err(|error| error.to_string())
+                .and_then(|mv| game.play(mv).map_err(|error| error.to_string()));
+            if let Err(error) = played {
+                self.out.send(&format!("info string invalid move {}: {}", uci, error));
+                break;
+            }
+        }
+        self.game = game;
+    }
+
+    /// Handle `go` by searching on another thread, which writes the best
+    /// move when it is done. Without any limit the search goes on until `stop`.
+    fn go(&mut self, args: &[&str]) {
+        self.stop_search();
+        let number = |name: &str| -> Option<u64> {
+            let at = args.iter().position(|&arg| arg == name)?;
+            args.get(at + 1)?.parse().ok()
+        };
+        let millis = |name: &str| number(name).map(Duration::from_millis);
+
+        let mut limit = Limit {
+            depth: number("depth").map(|depth| depth as u32),
+            nodes: number("nodes"),
+            time: millis("movetime")
+        };
+        let (clock, increment) = match self.game.active_color() {
+            Color::White => (millis("wtime"), millis("winc")),
+            Color::Black => (millis("btime"), millis("binc"))
+        };
+        if let (None, Some(clock)) = (limit.time, clock) {
+            let moves_to_go = number("movestogo").map(|moves| moves as u32);
+            limit.time = Limit::for_clock(clock, increment.unwrap_or_default(), moves_to_go).time;
+        }
+        let infinite = args.contains(&"infinite") || limit == Limit::default();
+        if infinite {
+            limit = Limit::default();
+        }
+
+        let game = self.game.clone();
+        let out = self.out.clone();
+        self.thinker.start(move |searcher, stop| {
+            let start = Instant::now();
+            let result = searcher.search_with(&game, limit, |result| out.send(&info(result, start.elapsed())));
+            // the best move of an infinite search may only be given once the GUI stops it
+            while infinite && !stop.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(1));
+            }
+            match result {
+                Some(result) => out.send(&format!("bestmove {}", game.to_uci(&result.mv()))),
+                None => out.send("bestmove 0000")
+            }
+        });
+    }
+
+    /// Stop the running search, if any, and wait for its best move.
+    fn stop_search(&mut self) {
+        self.thinker.stop();
+    }
+}
+
+/// Return the `info` line for the result of an iteration.
+fn info(result: &SearchResult, elapsed: Duration) -> String {
+    let score = match result.score() {
+        Score::Centipawns(centipawns) => format!("cp {}", centipawns),
+        Score::Mate(moves) => format!("mate {}", moves)
+    };
+    let millis = elapsed.as_millis().max(1) as u64;
+    let pv: Vec<String> = result.pv().iter().map(|mv| mv.to_string()).collect();
+    format!("info depth {} score {} nodes {} nps {} time {} pv {}",
+        result.depth(), score, result.nodes(), result.nodes() * 1000 / millis, millis, pv.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use super::Engine;
+    use crate::common::Protocol;
+    use crate::common::testing::{engine, run};
+
+    #[test]
+    fn handshake() {
+        let (mut engine, out) = engine(Engine::new);
+        let lines = run(&mut engine, &out, &["uci"]);
+        assert!(lines[0].starts_with("id name ellmar-chess"));
+        assert_eq!(lines.last().unwrap(), "uciok");
+        assert_eq!(run(&mut engine, &out, &["isready", "nonsense"]), ["readyok"]);
+        assert!(!engine.handle("quit"));
+    }
+
+    #[test]
+    fn positions_are_set_up() {
+        let (mut engine, out) = engine(Engine::new);
+        run(&mut engine, &out, &["position startpos moves e2e4 e7e5 g1f3"]);
+        assert_eq!(engine.game.to_fen(), "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2");
+
+        run(&mut engine, &out, &["position fen 4k3/8/8/8/8/8/4P3/4K3 w - - 0 1 moves e2e4"]);
+        assert_eq!(engine.game.to_fen(), "4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1");
+
+        assert!(run(&mut engine, &out, &["position fen 4k3/8/8/8/8/8/4P3/4K3 w - - moves e2e4"]).is_empty());
+        assert_eq!(engine.game.to_fen(), "4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1");
+        assert!(run(&mut engine, &out, &["position fen 4k3/8/8/8/8/8/4P3/4K3 b - -"]).is_empty());
+        assert_eq!(engine.game.to_fen(), "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1");
+
+        let lines = run(&mut engine, &out, &["position startpos moves e2e5"]);
+        assert!(lines[0].starts_with("info string invalid move e2e5"));
+        assert_eq!(engine.game.to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    }
+
+    #[test]
+    fn searches_give_a_best_move() {
+        let (mut engine, out) = engine(Engine::new);
+        run(&mut engine, &out, &["position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"]);
+        engine.handle("go depth 3");
+        engine.thinker.wait();
+        let lines = out.lines();
+        assert!(lines[0].starts_with("info depth 1 score mate 1"), "{:?}", lines);
+        assert_eq!(lines.last().unwrap(), "bestmove a1a8");
+
+        run(&mut engine, &out, &["position startpos"]);
+        engine.handle("go wtime 1000 btime 1000 winc 0 binc 0");
+        engine.stop_search();
+        assert!(out.lines().last().unwrap().starts_with("bestmove"));
+    }
+
+    #[test]
+    fn infinite_searches_wait_for_stop() {
+        let (mut engine, out) = engine(Engine::new);
+        run(&mut engine, &out, &["position startpos"]);
+        engine.handle("go infinite");
+        thread::sleep(Duration::from_millis(50));
+        assert!(out.lines().iter().all(|line| !line.starts_with("bestmove")));
+        assert_eq!(run(&mut engine, &out, &["isready"]), ["readyok"]);
+        let lines = run(&mut engine, &out, &["stop"]);
+        assert!(lines.last().unwrap().starts_with("bestmove"));
+    }
+
+    #[test]
+    fn weights_are_an_option() {
+        let (mut engine, out) = engine(Engine::new);
+        let lines = run(&mut engine, &out, &["setoption name Weights value /nonexistent/weights.txt"]);
+        assert!(lines[0].starts_with("info string cannot use weights"));
+        assert!(run(&mut engine, &out, &["setoption name Weights value <empty>"]).is_empty());
+        assert_eq!(run(&mut engine, &out, &["setoption name Ponder value true"]), ["info string unknown option Ponder"]);
+    }
+
+    #[test]
+    fn hash_is_an_option() {
+        let (mut engine, out) = engine(Engine::new);
+        assert!(run(&mut engine, &out, &["uci"]).contains(&"option name Hash type spin default 16 min 1 max 1024".to_string()));
+        assert!(run(&mut engine, &out, &["setoption name Hash value 1", "setoption name Clear Hash"]).is_empty());
+        assert_eq!(run(&mut engine, &out, &["setoption name Hash value 0"]), ["info string invalid hash size 0"]);
+        assert_eq!(run(&mut engine, &out, &["setoption name Hash value lots"]), ["info string invalid hash size lots"]);
+        assert_eq!(run(&mut engine, &out, &["setoption name Hash value 1000000"]), ["info string invalid hash size 1000000"]);
+
+        run(&mut engine, &out, &["ucinewgame", "position startpos"]);
+        engine.handle("go depth 2");
+        engine.stop_search();
+        assert!(out.lines().last().unwrap().starts_with("bestmove"));
+    }
+}
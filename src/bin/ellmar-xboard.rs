@@ -0,0 +1,435 @@
+//! A chess engine speaking the Chess Engine Communication Protocol of
+//! XBoard and WinBoard on standard input and output.
+
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use ellmar_chess::{Color, Game, Limit, Move, Outcome, Score, SearchResult, Termination, MAX_HASH_MEGABYTES};
+
+use common::{Output, Protocol, Thinker};
+
+mod common;
+
+/// How long to think about a move when no time control was given.
+const DEFAULT_MOVE_TIME: Duration = Duration::from_secs(5);
+
+fn main() {
+    common::serve(Engine::new(Output::new(io::stdout())));
+}
+
+/// The state of the engine between the commands of the GUI.
+struct Engine<W> {
+    /// The game so far, without the move the engine is thinking about.
+    game: Game,
+    /// The colour the engine plays, None in force mode.
+    color: Option<Color>,
+    /// The number of moves in the game before each move of the engine, to
+    /// tell its moves from the user's when they are taken back.
+    engine_moves: Vec<usize>,
+    /// Searches on its own thread, which hands back the move it played.
+    thinker: Thinker<Option<Move>>,
+    /// Set before stopping the search to throw its move away.
+    discard: Arc<AtomicBool>,
+    clock: Clock,
+    /// Whether to show what the engine is thinking.
+    post: bool,
+    out: Output<W>
+}
+
+/// The time control set by the GUI.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+struct Clock {
+    /// The number of moves to make before time is added, 0 if it never is.
+    moves_per_session: u32,
+    /// Added to the clock after every move.
+    increment: Duration,
+    /// The time left on the engine's clock, if known.
+    remaining: Option<Duration>,
+    /// The number of moves the engine made since the time control was set.
+    moves_made: u32,
+    /// Fixed time for every move, set with `st`.
+    move_time: Option<Duration>,
+    /// Fixed depth for every move, set with `sd`.
+    depth: Option<u32>
+}
+
+impl<W: Write + Send + 'static> Protocol for Engine<W> {
+    fn handle(&mut self, line: &str) -> bool {
+        let (command, args) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+        let args = args.trim();
+        match command {
+            // commands that may come while the engine is thinking
+            "ping" => return self.reply(&format!("pong {}", args)),
+            "time" => {
+                self.clock.remaining = args.parse().ok().map(|centis: u64| Duration::from_millis(10 * centis));
+                return true;
+            },
+            "otim" | "" => return true,
+            "post" | "nopost" => {
+                self.post = command == "post";
+                return true;
+            },
+            "?" => {
+                self.stop_search(false);
+                return true;
+            },
+            "quit" => return false,
+            _ => {}
+        }
+
+        self.stop_search(true);
+        match command {
+            "protover" => {
+                let features = format!("feature myname=\"{} {}\" usermove=1 setboard=1 ping=1 memory=1 colors=0 sigint=0 sigterm=0 done=1",
+                    env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+                self.out.send(&features);
+            },
+            "new" => {
+                self.thinker.searcher().clear_hash();
+                self.game = Game::new();
+                self.engine_moves.clear();
+                self.color = Some(Color::Black);
+                self.clock.move_time = None;
+                self.clock.depth = None;
+                self.clock.moves_made = 0;
+            },
+            "force" | "result" => self.color = None,
+            "go" => {
+                self.color = Some(self.game.active_color());
+                self.think();
+            },
+            "usermove" => self.user_move(args),
+            "setboard" => match Game::from_fen(args) {
+                Ok(game) => {
+                    self.game = game;
+                    self.engine_moves.clear();
+                },
+                Err(error) => self.out.send(&format!("tellusererror Illegal position: {}", error))
+            },
+            "level" => self.set_level(args),
+            "st" => self.clock.move_time = args.parse().ok().map(Duration::from_secs),
+            "sd" => self.clock.depth = args.parse().ok(),
+            "memory" => match args.parse::<usize>() {
+                Ok(megabytes) if (1..=MAX_HASH_MEGABYTES).contains(&megabytes) => self.thinker.searcher().set_hash_size(megabytes),
+                _ => self.out.send(&format!("Error (invalid memory size): {}", line.trim()))
+            },
+            "undo" => self.take_back(1, line.trim()),
+            "remove" => self.take_back(2, line.trim()),
+            // commands that are part of the protocol, but do not matter here
+            "xboard" | "accepted" | "rejected" | "random" | "hard" | "easy" | "computer" | "name" | "rating"
+                | "draw" | "ics" => {},
+            _ => self.out.send(&format!("Error (unknown command): {}", command))
+        }
+        true
+    }
+
+    fn quit(&mut self) {
+        self.stop_search(true);
+    }
+}
+
+impl<W: Write + Send + 'static> Engine<W> {
+    fn new(out: Output<W>) -> Engine<W> {
+        Engine {
+            game: Game::new(),
+            color: Some(Color::Black),
+            engine_moves: vec!(),
+            thinker: Thinker::new(),
+            discard: Arc::new(AtomicBool::new(false)),
+            clock: Clock::default(),
+            post: false,
+            out
+        }
+    }
+
+    /// Write a line and go on.
+    fn reply(&self, line: &str) -> bool {
+        self.out.send(line);
+        true
+    }
+
+    /// Handle `usermove <move>`, answering with a move of the engine if it is its turn.
+    fn user_move(&mut self, uci: &str) {
+        let played = self.game.parse_uci(uci).map_err(|error| error.to_string())
+            .and_then(|mv| self.game.play(mv).map_err(|error| error.to_string()));
+        if played.is_err() {
+            return self.out.send(&format!("Illegal move: {}", uci));
+        }
+        if let Some(result) = result_line(&self.game) {
+            self.out.send(&result);
+        }
+        else if self.color == Some(self.game.active_color()) {
+            self.think();
+        }
+    }
+
+    /// Take back the last `moves` moves, or none if the game does not allow it.
+    fn take_back(&mut self, moves: usize, command: &str) {
+        let mut game = self.game.clone();
+        for _ in 0..moves {
+            if game.undo_move().is_none() {
+                return self.out.send(&format!("Error (cannot undo): {}", command));
+            }
+        }
+        self.game = game;
+        // the moves taken back no longer count for the time control
+        while self.engine_moves.last().is_some_and(|&before| before >= self.game.history().len()) {
+            self.engine_moves.pop();
+            self.clock.moves_made = self.clock.moves_made.saturating_sub(1);
+        }
+    }
+
+    /// Handle `level <moves> <base> <increment>`, where the base time is
+    /// given in minutes, or minutes and seconds such as `0:30`, and the
+    /// increment in seconds.
+    fn set_level(&mut self, args: &str) {
+        let fields: Vec<&str> = args.split_whitespace().collect();
+        let seconds = |field: &str| -> Option<Duration> {
+            let (minutes, seconds) = field.split_once(':').unwrap_or((field, "0"));
+            Some(Duration::from_secs(60 * minutes.parse::<u64>().ok()? + seconds.parse::<u64>().ok()?))
+        };
+        match fields.as_slice() {
+            [moves, base, increment] => {
+                let increment = increment.parse::<f64>().ok().filter(|increment| *increment >= 0.0).map(Duration::from_secs_f64);
+                match (moves.parse(), seconds(base), increment) {
+                    (Ok(moves), Some(base), Some(increment)) => {
+                        self.clock = Clock {moves_per_session: moves, increment, remaining: Some(base), moves_made: 0, ..self.clock};
+                    },
+                    _ => self.out.send(&format!("Error (invalid time control): level {}", args))
+                }
+            },
+            _ => self.out.send(&format!("Error (invalid time control): level {}", args))
+        }
+    }
+
+    /// Return how long and deep to think about the next move.
+    fn limit(&self) -> Limit {
+        let mut limit = Limit {depth: self.clock.depth, nodes: None, time: self.clock.move_time};
+        if limit.time.is_none() {
+            if let Some(remaining) = self.clock.remaining {
+                let moves = self.clock.moves_per_session;
+                let moves_to_go = if moves > 0 {Some(moves - self.clock.moves_made % moves)} else {None};
+                limit.time = Limit::for_clock(remaining, self.clock.increment, moves_to_go).time;
+            }
+            else if limit.depth.is_none() {
+                limit.time = Some(DEFAULT_MOVE_TIME);
+            }
+        }
+        limit
+    }
+
+    /// Search for a move on another thread, which sends it when it is done.
+    fn think(&mut self) {
+        if self.game.outcome().is_some() {
+            return;
+        }
+        let limit = self.limit();
+        let mut game = self.game.clone();
+        let (out, discard, post) = (self.out.clone(), Arc::clone(&self.discard), self.post);
+        discard.store(false, Ordering::Relaxed);
+        self.thinker.start(move |searcher, _| {
+            let start = Instant::now();
+            let result = searcher.search_with(&game, limit, |result| if post {
+                out.send(&thinking(result, start.elapsed()));
+            });
+            let mv = result.map(|result| result.mv()).filter(|_| !discard.load(Ordering::Relaxed));
+            if let Some(mv) = mv {
+                out.send(&format!("move {}", game.to_uci(&mv)));
+                game.play(mv).expect("the search finds legal moves");
+                if let Some(result) = result_line(&game) {
+                    out.send(&result);
+                }
+            }
+            mv
+        });
+    }
+
+    /// Stop the running search, if any, and play the move it found unless
+    /// `discard` is set.
+    fn stop_search(&mut self, discard: bool) {
+        self.discard.store(discard, Ordering::Relaxed);
+        if let Some(Some(mv)) = self.thinker.stop() {
+            self.engine_moves.push(self.game.history().len());
+            self.game.play(mv).expect("the search finds legal moves");
+            self.clock.moves_made += 1;
+        }
+    }
+}
+
+/// Return the thinking output for the result of an iteration: the depth,
+/// the score in centipawns, the time in centiseconds, the nodes and the
+/// principal variation. Mates are given as 100000 plus the moves to mate.
+fn thinking(result: &SearchResult, elapsed: Duration) -> String {
+    let score = match result.score() {
+        Score::Centipawns(centipawns) => centipawns,
+        Score::Mate(moves) if moves > 0 => 100_000 + moves,
+        Score::Mate(moves) => -100_000 + moves
+    };
+    let pv: Vec<String> = result.pv().iter().map(|mv| mv.to_string()).collect();
+    format!("{} {} {} {} {}", result.depth(), score, elapsed.as_millis() / 10, result.nodes(), pv.join(" "))
+}
+
+/// Return the line announcing the result if the game is over.
+fn result_line(game: &Game) -> Option<String> {
+    let outcome = game.outcome()?;
+    let reason = match (game.termination()?, outcome) {
+        (Termination::Checkmate, Outcome::WhiteWins) => "White mates",
+        (Termination::Checkmate, _) => "Black mates",
+        (Termination::Stalemate, _) => "Stalemate",
+        (Termination::InsufficientMaterial, _) => "Insufficient material",
+        (Termination::SeventyFiveMoveRule, _) => "75-move rule",
+        (Termination::FivefoldRepetition, _) => "Fivefold repetition",
+        _ => "Game over"
+    };
+    Some(format!("{} {{{}}}", outcome, reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use super::{Clock, Engine};
+    use crate::common::{Output, Protocol};
+    use crate::common::testing::{engine, run};
+    use ellmar_chess::Color;
+
+    /// Wait for the engine to finish thinking and return what it wrote.
+    fn answer(engine: &Engine<Vec<u8>>, out: &Output<Vec<u8>>) -> Vec<String> {
+        engine.thinker.wait();
+        out.lines()
+    }
+
+    #[test]
+    fn handshake() {
+        let (mut engine, out) = engine(Engine::new);
+        let lines = run(&mut engine, &out, &["xboard", "protover 2"]);
+        assert!(lines[0].starts_with("feature myname=\"ellmar-chess"));
+        assert!(lines[0].ends_with("done=1"));
+        assert!(lines[0].contains(" memory=1 "));
+        assert_eq!(run(&mut engine, &out, &["ping 7", "nonsense"]), ["pong 7", "Error (unknown command): nonsense"]);
+        assert!(run(&mut engine, &out, &["memory 1"]).is_empty());
+        assert_eq!(run(&mut engine, &out, &["memory lots"]), ["Error (invalid memory size): memory lots"]);
+        assert_eq!(run(&mut engine, &out, &["memory 0"]), ["Error (invalid memory size): memory 0"]);
+        assert_eq!(run(&mut engine, &out, &["memory 1000000"]), ["Error (invalid memory size): memory 1000000"]);
+        assert!(!engine.handle("quit"));
+    }
+
+    #[test]
+    fn engine_answers_user_moves() {
+        let (mut engine, out) = engine(Engine::new);
+        run(&mut engine, &out, &["new", "sd 2"]);
+        engine.handle("usermove e2e4");
+        let lines = answer(&engine, &out);
+        assert!(lines.last().unwrap().starts_with("move "), "{:?}", lines);
+        assert_eq!(engine.game.history().len(), 1);
+        engine.handle("usermove g1f3");
+        assert_eq!(engine.game.history().len(), 3);
+        answer(&engine, &out);
+
+        assert_eq!(run(&mut engine, &out, &["usermove e2e5"]), ["Illegal move: e2e5"]);
+    }
+
+    #[test]
+    fn force_mode_only_records_moves() {
+        let (mut engine, out) = engine(Engine::new);
+        assert!(run(&mut engine, &out, &["new", "force", "usermove e2e4", "usermove e7e5"]).is_empty());
+        assert_eq!(engine.game.history().len(), 2);
+
+        // go makes the engine play the side to move
+        run(&mut engine, &out, &["sd 1"]);
+        engine.handle("go");
+        assert_eq!(engine.color, Some(Color::White));
+        assert!(answer(&engine, &out).last().unwrap().starts_with("move "));
+        run(&mut engine, &out, &["force"]);
+        assert_eq!(engine.game.active_color(), Color::Black);
+    }
+
+    #[test]
+    fn positions_are_set_up_and_finished_games_reported() {
+        let (mut engine, out) = engine(Engine::new);
+        run(&mut engine, &out, &["new", "force", "setboard 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "post", "sd 3"]);
+        engine.handle("go");
+        let lines = answer(&engine, &out);
+        assert!(lines[0].starts_with("1 100001 "), "{:?}", lines);
+        assert_eq!(lines[lines.len() - 2..], ["move a1a8", "1-0 {White mates}"]);
+
+        assert!(run(&mut engine, &out, &["setboard 8/8/8/8 w - - 0 1"])[0].starts_with("tellusererror Illegal position"));
+    }
+
+    #[test]
+    fn time_controls_are_read() {
+        let (mut engine, out) = engine(Engine::new);
+        run(&mut engine, &out, &["level 40 5 0", "time 6000", "otim 6000"]);
+        assert_eq!(engine.clock, Clock {
+            moves_per_session: 40,
+            remaining: Some(Duration::from_secs(60)),
+            ..Clock::default()
+        });
+        assert_eq!(engine.limit().time, Some(Duration::from_millis(1500)));
+
+        run(&mut engine, &out, &["level 0 0:30 2"]);
+        assert_eq!(engine.clock.remaining, Some(Duration::from_secs(30)));
+        assert_eq!(engine.clock.increment, Duration::from_secs(2));
+        assert_eq!(run(&mut engine, &out, &["level 0 x 2"]), ["Error (invalid time control): level 0 x 2"]);
+
+        run(&mut engine, &out, &["st 3"]);
+        assert_eq!(engine.limit().time, Some(Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn moves_to_go_count_from_the_time_control() {
+        let (mut engine, out) = engine(Engine::new);
+        run(&mut engine, &out, &["new", "force", "setboard rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 20",
+            "usermove e2e4", "usermove e7e5", "level 40 5 0", "time 6000", "sd 1"]);
+        assert_eq!(engine.limit().time, Some(Duration::from_millis(1500)));
+
+        engine.handle("go");
+        answer(&engine, &out);
+        run(&mut engine, &out, &["force"]);
+        assert_eq!(engine.limit().time, Some(Duration::from_secs(60) / 39));
+
+        // the black engine has made no move yet when it answers the first one
+        run(&mut engine, &out, &["new", "level 40 5 0", "time 6000", "sd 1"]);
+        assert_eq!(engine.clock.moves_made, 0);
+        engine.handle("usermove e2e4");
+        answer(&engine, &out);
+        run(&mut engine, &out, &["force"]);
+        assert_eq!(engine.clock.moves_made, 1);
+    }
+
+    #[test]
+    fn take_backs_count_the_engines_moves_back() {
+        let (mut engine, out) = engine(Engine::new);
+        run(&mut engine, &out, &["new", "level 40 5 0", "time 6000", "sd 1"]);
+        engine.handle("usermove e2e4");
+        answer(&engine, &out);
+        run(&mut engine, &out, &["force", "usermove d2d4"]);
+        assert_eq!(engine.clock.moves_made, 1);
+
+        assert!(run(&mut engine, &out, &["undo"]).is_empty());
+        assert_eq!((engine.game.history().len(), engine.clock.moves_made), (2, 1));
+        assert!(run(&mut engine, &out, &["remove"]).is_empty());
+        assert_eq!((engine.game.history().len(), engine.clock.moves_made), (0, 0));
+        assert_eq!(engine.limit().time, Some(Duration::from_millis(1500)));
+
+        assert_eq!(run(&mut engine, &out, &["usermove e2e4", "remove"]), ["Error (cannot undo): remove"]);
+        assert_eq!(engine.game.history().len(), 1);
+        assert_eq!(run(&mut engine, &out, &["undo", "undo"]), ["Error (cannot undo): undo"]);
+    }
+
+    #[test]
+    fn results_and_move_now() {
+        let (mut engine, out) = engine(Engine::new);
+        run(&mut engine, &out, &["new", "st 100", "go"]);
+        thread::sleep(Duration::from_millis(20));
+        let lines = run(&mut engine, &out, &["?"]);
+        assert!(lines[0].starts_with("move "), "{:?}", lines);
+        assert_eq!(engine.game.history().len(), 1);
+
+        run(&mut engine, &out, &["result 1/2-1/2 {agreed}"]);
+        assert_eq!(engine.color, None);
+    }
+}
@@ -0,0 +1,165 @@
+use crate::{Color, Square};
+
+/// A set of squares, bit 0 standing for a1, bit 1 for b1, ..., bit 63 for h8.
+pub(crate) type Bitboard = u64;
+
+/// The light squares: b1, d1, ..., a2, c2, ..., h8.
+pub(crate) const LIGHT_SQUARES: Bitboard = 0x55AA_55AA_55AA_55AA;
+
+/// Directions `[file, rank]` pieces slide along. The first four lead to
+/// higher square indices, the last four to lower ones.
+const DIRECTIONS: [[i8; 2]; 8] = [
+    [0, 1], [1, 0], [1, 1], [-1, 1],
+    [0, -1], [-1, 0], [-1, -1], [1, -1]
+];
+
+/// Indices into `DIRECTIONS` of the directions a rook slides along.
+const ROOK_DIRECTIONS: [usize; 4] = [0, 1, 4, 5];
+
+/// Indices into `DIRECTIONS` of the directions a bishop slides along.
+const BISHOP_DIRECTIONS: [usize; 4] = [2, 3, 6, 7];
+
+/// Squares a knight on the given square jumps to.
+pub(crate) const KNIGHT_ATTACKS: [Bitboard; 64] = step_attacks(&[
+    [2, 1], [2, -1], [-2, 1], [-2, -1],
+    [1, 2], [-1, 2], [1, -2], [-1, -2]
+]);
+
+/// Squares a king on the given square steps to.
+pub(crate) const KING_ATTACKS: [Bitboard; 64] = step_attacks(&[
+    [1, 0], [1, 1], [0, 1], [-1, 1],
+    [-1, 0], [-1, -1], [0, -1], [1, -1]
+]);
+
+/// Squares a white pawn on the given square attacks.
+const WHITE_PAWN_ATTACKS: [Bitboard; 64] = step_attacks(&[[-1, 1], [1, 1]]);
+
+/// Squares a black pawn on the given square attacks.
+const BLACK_PAWN_ATTACKS: [Bitboard; 64] = step_attacks(&[[-1, -1], [1, -1]]);
+
+/// `RAYS[direction][square]` holds all squares from `square` to the edge
+/// of the board in the direction `DIRECTIONS[direction]`, without `square`.
+const RAYS: [[Bitboard; 64]; 8] = rays();
+
+/// Return the set holding only `square`.
+pub(crate) fn bit(square: Square) -> Bitboard {
+    1 << square.index()
+}
+
+/// Iterate over the squares of a set, from a1 to h8.
+pub(crate) fn squares(mut bitboard: Bitboard) -> impl Iterator<Item = Square> {
+    std::iter::from_fn(move || {
+        let square = first_square(bitboard)?;
+        bitboard &= bitboard - 1;
+        Some(square)
+    })
+}
+
+/// Return the square of the set closest to a1, None if it is empty.
+pub(crate) fn first_square(bitboard: Bitboard) -> Option<Square> {
+    Square::from_index(bitboard.trailing_zeros() as usize)
+}
+
+/// Return the squares a pawn of the given colour on `square` attacks.
+pub(crate) fn pawn_attacks(color: Color, square: Square) -> Bitboard {
+    match color {
+        Color::White => WHITE_PAWN_ATTACKS[square.index()],
+        Color::Black => BLACK_PAWN_ATTACKS[square.index()]
+    }
+}
+
+/// Return the squares a rook on `square` attacks, the first piece of
+/// `occupied` along every line included.
+pub(crate) fn rook_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    ROOK_DIRECTIONS.iter().fold(0, |attacks, &direction| attacks | ray_attacks(square, occupied, direction))
+}
+
+/// Return the squares a bishop on `square` attacks, the first piece of
+/// `occupied` along every diagonal included.
+pub(crate) fn bishop_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    BISHOP_DIRECTIONS.iter().fold(0, |attacks, &direction| attacks | ray_attacks(square, occupied, direction))
+}
+
+/// Return the squares along the ray up to and including the first piece of `occupied`.
+fn ray_attacks(square: Square, occupied: Bitboard, direction: usize) -> Bitboard {
+    let ray = RAYS[direction][square.index()];
+    let blockers = ray & occupied;
+    if blockers == 0 {
+        return ray;
+    }
+    // the first blocker is the lowest bit on rays leading up the board, the highest otherwise
+    let first = if direction < 4 {blockers.trailing_zeros()} else {63 - blockers.leading_zeros()};
+    ray ^ RAYS[direction][first as usize]
+}
+
+/// Return for every square the squares reached by one of the steps `[file, rank]`.
+const fn step_attacks(offsets: &[[i8; 2]]) -> [Bitboard; 64] {
+    let mut attacks = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        let mut i = 0;
+        while i < offsets.len() {
+            let file = (square % 8) as i8 + offsets[i][0];
+            let rank = (square / 8) as i8 + offsets[i][1];
+            if file >= 0 && file < 8 && rank >= 0 && rank < 8 {
+                attacks[square] |= 1 << (rank * 8 + file);
+            }
+            i += 1;
+        }
+        square += 1;
+    }
+    attacks
+}
+
+const fn rays() -> [[Bitboard; 64]; 8] {
+    let mut rays = [[0; 64]; 8];
+    let mut direction = 0;
+    while direction < 8 {
+        let mut square = 0;
+        while square < 64 {
+            let mut file = (square % 8) as i8 + DIRECTIONS[direction][0];
+            let mut rank = (square / 8) as i8 + DIRECTIONS[direction][1];
+            while file >= 0 && file < 8 && rank >= 0 && rank < 8 {
+                rays[direction][square] |= 1 << (rank * 8 + file);
+                file += DIRECTIONS[direction][0];
+                rank += DIRECTIONS[direction][1];
+            }
+            square += 1;
+        }
+        direction += 1;
+    }
+    rays
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bishop_attacks, bit, pawn_attacks, rook_attacks, squares, KING_ATTACKS, KNIGHT_ATTACKS};
+    use crate::{Color, Square};
+
+    fn sq(square: &str) -> Square {
+        square.parse().unwrap()
+    }
+
+    fn names(bitboard: u64) -> Vec<String> {
+        squares(bitboard).map(|square| square.to_string()).collect()
+    }
+
+    #[test]
+    fn stepping_pieces_stay_on_board() {
+        assert_eq!(names(KNIGHT_ATTACKS[sq("a1").index()]), ["c2", "b3"]);
+        assert_eq!(KNIGHT_ATTACKS[sq("e4").index()].count_ones(), 8);
+        assert_eq!(names(KING_ATTACKS[sq("h8").index()]), ["g7", "h7", "g8"]);
+        assert_eq!(names(pawn_attacks(Color::White, sq("a2"))), ["b3"]);
+        assert_eq!(names(pawn_attacks(Color::Black, sq("e5"))), ["d4", "f4"]);
+    }
+
+    #[test]
+    fn sliding_pieces_stop_at_the_first_piece() {
+        let occupied = bit(sq("e6")) | bit(sq("c4")) | bit(sq("e2"));
+        assert_eq!(names(rook_attacks(sq("e4"), occupied)), ["e2", "e3", "c4", "d4", "f4", "g4", "h4", "e5", "e6"]);
+        assert_eq!(rook_attacks(sq("a1"), 0).count_ones(), 14);
+
+        let occupied = bit(sq("c6")) | bit(sq("g2"));
+        assert_eq!(names(bishop_attacks(sq("e4"), occupied)), ["b1", "c2", "g2", "d3", "f3", "d5", "f5", "c6", "g6", "h7"]);
+    }
+}
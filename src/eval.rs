@@ -0,0 +1,464 @@
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::{Color, File, Piece, Rank, Square};
+use crate::bitboard::{self, Bitboard, KING_ATTACKS, KNIGHT_ATTACKS};
+use crate::position::Position;
+
+/// Judges positions without searching, for the search to compare the
+/// positions at the end of the lines it looks at.
+pub trait Evaluator {
+    /// Return the value of the position in centipawns for the side to move.
+    fn evaluate(&self, position: &Position) -> i32;
+}
+
+/// The evaluation used unless another one is given: material, where the
+/// pieces stand, the pawn structure, the safety of the kings and how much
+/// the pieces can move, weighted by its `Weights`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DefaultEvaluator {
+    weights: Weights
+}
+
+/// The weights of `DefaultEvaluator` in centipawns. They can be read from
+/// a file with `Weights::load`, with one `name = value` line for every
+/// weight to change and `#` starting a comment, e.g. `passed_pawn = 25`.
+/// Writing the weights gives the same format.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Weights {
+    pub pawn: i32,
+    pub knight: i32,
+    pub bishop: i32,
+    pub rook: i32,
+    pub queen: i32,
+    /// For having both bishops.
+    pub bishop_pair: i32,
+    /// How much the piece-square tables count, in percent.
+    pub piece_squares: i32,
+    /// For every pawn behind another of the same colour on its file.
+    pub doubled_pawn: i32,
+    /// For every pawn without pawns of the same colour on the files next to it.
+    pub isolated_pawn: i32,
+    /// For every rank a pawn that no pawn of the other side can stop has advanced.
+    pub passed_pawn: i32,
+    /// For every pawn in front of its own king while there are pieces to attack it.
+    pub king_shelter: i32,
+    /// For every square next to the king of the other side a piece attacks.
+    pub king_attack: i32,
+    /// For every square a knight, bishop, rook or queen can move to.
+    pub mobility: i32
+}
+
+/// Why weights could not be read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WeightsError {
+    /// The file could not be read.
+    Io(std::io::ErrorKind),
+    /// The line with the given number is not of the form `name = value`.
+    InvalidLine(usize),
+    /// The line with the given number names a weight that does not exist.
+    UnknownWeight(usize, String),
+    /// The value on the line with the given number is not a whole number.
+    InvalidValue(usize, String)
+}
+
+/// The piece-square tables in centipawns, from a8 to h1 as the board is
+/// seen by White, for the kind indices of `Position` but the king, which
+/// has `KING_MIDDLEGAME` and `KING_ENDGAME`. Black uses them upside down.
+const PIECE_SQUARES: [[i32; 64]; 5] = [
+    // queen
+    [-20, -10, -10,  -5,  -5, -10, -10, -20,
+     -10,   0,   0,   0,   0,   0,   0, -10,
+     -10,   0,   5,   5,   5,   5,   0, -10,
+      -5,   0,   5,   5,   5,   5,   0,  -5,
+       0,   0,   5,   5,   5,   5,   0,  -5,
+     -10,   5,   5,   5,   5,   5,   0, -10,
+     -10,   0,   5,   0,   0,   0,   0, -10,
+     -20, -10, -10,  -5,  -5, -10, -10, -20],
+    // rook
+    [  0,   0,   0,   0,   0,   0,   0,   0,
+       5,  10,  10,  10,  10,  10,  10,   5,
+      -5,   0,   0,   0,   0,   0,   0,  -5,
+      -5,   0,   0,   0,   0,   0,   0,  -5,
+      -5,   0,   0,   0,   0,   0,   0,  -5,
+      -5,   0,   0,   0,   0,   0,   0,  -5,
+      -5,   0,   0,   0,   0,   0,   0,  -5,
+       0,   0,   0,   5,   5,   0,   0,   0],
+    // knight
+    [-50, -40, -30, -30, -30, -30, -40, -50,
+     -40, -20,   0,   0,   0,   0, -20, -40,
+     -30,   0,  10,  15,  15,  10,   0, -30,
+     -30,   5,  15,  20,  20,  15,   5, -30,
+     -30,   0,  15,  20,  20,  15,   0, -30,
+     -30,   5,  10,  15,  15,  10,   5, -30,
+     -40, -20,   0,   5,   5,   0, -20, -40,
+     -50, -40, -30, -30, -30, -30, -40, -50],
+    // bishop
+    [-20, -10, -10, -10, -10, -10, -10, -20,
+     -10,   0,   0,   0,   0,   0,   0, -10,
+     -10,   0,   5,  10,  10,   5,   0, -10,
+     -10,   5,   5,  10,  10,   5,   5, -10,
+     -10,   0,  10,  10,  10,  10,   0, -10,
+     -10,  10,  10,  10,  10,  10,  10, -10,
+     -10,   5,   0,   0,   0,   0,   5, -10,
+     -20, -10, -10, -10, -10, -10, -10, -20],
+    // pawn
+    [  0,   0,   0,   0,   0,   0,   0,   0,
+      50,  50,  50,  50,  50,  50,  50,  50,
+      10,  10,  20,  30,  30,  20,  10,  10,
+       5,   5,  10,  25,  25,  10,   5,   5,
+       0,   0,   0,  20,  20,   0,   0,   0,
+       5,  -5, -10,   0,   0, -10,  -5,   5,
+       5,  10,  10, -20, -20,  10,  10,   5,
+       0,   0,   0,   0,   0,   0,   0,   0]
+];
+
+/// Where the king stands well while there are pieces to attack it: behind its pawns.
+const KING_MIDDLEGAME: [i32; 64] = [
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -20, -30, -30, -40, -40, -30, -30, -20,
+    -10, -20, -20, -20, -20, -20, -20, -10,
+     20,  20,   0,   0,   0,   0,  20,  20,
+     20,  30,  10,   0,   0,  10,  30,  20
+];
+
+/// Where the king stands well once most pieces are gone: in the centre.
+const KING_ENDGAME: [i32; 64] = [
+    -50, -40, -30, -20, -20, -30, -40, -50,
+    -30, -20, -10,   0,   0, -10, -20, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -30,   0,   0,   0,   0, -30, -30,
+    -50, -30, -30, -30, -30, -30, -30, -50
+];
+
+/// The phase of a game with all pieces on the board, see `phase`.
+const OPENING_PHASE: i32 = 24;
+
+/// The weights unless others are given, from which `Piece::value` also
+/// takes the values of the pieces.
+pub(crate) const DEFAULT_WEIGHTS: Weights = Weights {
+    pawn: 100,
+    knight: 320,
+    bishop: 330,
+    rook: 500,
+    queen: 900,
+    bishop_pair: 30,
+    piece_squares: 100,
+    doubled_pawn: -15,
+    isolated_pawn: -10,
+    passed_pawn: 10,
+    king_shelter: 10,
+    king_attack: 5,
+    mobility: 3
+};
+
+impl Default for Weights {
+    fn default() -> Self {
+        DEFAULT_WEIGHTS
+    }
+}
+
+impl Weights {
+    /// Read the weights from a file, see `Weights`. Weights the file does not mention keep their default.
+    pub fn load(path: impl AsRef<Path>) -> Result<Weights, WeightsError> {
+        std::fs::read_to_string(path).map_err(|error| WeightsError::Io(error.kind()))?.parse()
+    }
+
+    /// Return the weight of the material of the piece.
+    pub fn value(&self, piece: Piece) -> i32 {
+        match piece {
+            Piece::King(_) => 0,
+            Piece::Queen(_) => self.queen,
+            Piece::Rook(_) => self.rook,
+            Piece::Knight(_) => self.knight,
+            Piece::Bishop(_) => self.bishop,
+            Piece::Pawn(_) => self.pawn
+        }
+    }
+
+    /// Return every weight with its name.
+    fn named(&mut self) -> [(&'static str, &mut i32); 13] {
+        [
+            ("pawn", &mut self.pawn),
+            ("knight", &mut self.knight),
+            ("bishop", &mut self.bishop),
+            ("rook", &mut self.rook),
+            ("queen", &mut self.queen),
+            ("bishop_pair", &mut self.bishop_pair),
+            ("piece_squares", &mut self.piece_squares),
+            ("doubled_pawn", &mut self.doubled_pawn),
+            ("isolated_pawn", &mut self.isolated_pawn),
+            ("passed_pawn", &mut self.passed_pawn),
+            ("king_shelter", &mut self.king_shelter),
+            ("king_attack", &mut self.king_attack),
+            ("mobility", &mut self.mobility)
+        ]
+    }
+}
+
+impl FromStr for Weights {
+    type Err = WeightsError;
+
+    /// Read the weights in the format of `Weights`, starting from the defaults.
+    fn from_str(s: &str) -> Result<Weights, WeightsError> {
+        let mut weights = Weights::default();
+        for (number, line) in s.lines().enumerate().map(|(i, line)| (i + 1, line)) {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (name, value) = line.split_once('=').ok_or(WeightsError::InvalidLine(number))?;
+            let (name, value) = (name.trim(), value.trim());
+            let value = value.parse().map_err(|_| WeightsError::InvalidValue(number, value.to_string()))?;
+            let mut named = weights.named();
+            let (_, weight) = named.iter_mut()
+                .find(|(other, _)| *other == name)
+                .ok_or_else(|| WeightsError::UnknownWeight(number, name.to_string()))?;
+            **weight = value;
+        }
+        Ok(weights)
+    }
+}
+
+impl fmt::Display for Weights {
+    /// Write one `name = value` line for every weight.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, value) in self.clone().named() {
+            writeln!(f, "{} = {}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for WeightsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WeightsError::Io(kind) => write!(f, "the weights could not be read: {}", kind),
+            WeightsError::InvalidLine(line) => write!(f, "line {}: expected \"name = value\"", line),
+            WeightsError::UnknownWeight(line, name) => write!(f, "line {}: there is no weight \"{}\"", line, name),
+            WeightsError::InvalidValue(line, value) => write!(f, "line {}: \"{}\" is not a whole number", line, value)
+        }
+    }
+}
+
+impl std::error::Error for WeightsError {}
+
+impl DefaultEvaluator {
+    /// The evaluation with the default weights.
+    pub fn new() -> DefaultEvaluator {
+        DefaultEvaluator::default()
+    }
+
+    /// The evaluation with the given weights.
+    pub fn with_weights(weights: Weights) -> DefaultEvaluator {
+        DefaultEvaluator {weights}
+    }
+
+    /// Return the weights used.
+    pub fn weights(&self) -> &Weights {
+        &self.weights
+    }
+
+    /// Return the value of the pieces of one colour for that colour.
+    fn evaluate_side(&self, position: &Position, color: Color, phase: i32) -> i32 {
+        let weights = &self.weights;
+        let own = position.pieces_of_color(color);
+        let occupied = position.occupied();
+        let enemy_king = bitboard::first_square(position.pieces_of(Piece::King(color.opposite())));
+        let enemy_king_zone = enemy_king.map_or(0, |square| KING_ATTACKS[square.index()] | bitboard::bit(square));
+
+        let mut value = 0;
+        let mut squares = 0;
+        let mut zone_attacks = 0;
+        for square in bitboard::squares(own) {
+            let piece = position.get_piece(square).expect("the square is occupied");
+            value += weights.value(piece);
+            squares += piece_square(piece, square, phase);
+
+            let attacks = match piece {
+                Piece::Knight(_) => KNIGHT_ATTACKS[square.index()],
+                Piece::Bishop(_) => bitboard::bishop_attacks(square, occupied),
+                Piece::Rook(_) => bitboard::rook_attacks(square, occupied),
+                Piece::Queen(_) => bitboard::bishop_attacks(square, occupied) | bitboard::rook_attacks(square, occupied),
+                _ => continue
+            };
+            value += weights.mobility * (attacks & !own).count_ones() as i32;
+            zone_attacks += (attacks & enemy_king_zone).count_ones() as i32;
+        }
+        value += squares * weights.piece_squares / 100;
+        if position.pieces_of(Piece::Bishop(color)).count_ones() >= 2 {
+            value += weights.bishop_pair;
+        }
+
+        // attacking and sheltering the king matters less the fewer pieces are left
+        let shelter = bitboard::first_square(position.pieces_of(Piece::King(color)))
+            .map_or(0, |king| shelter_squares(color, king) & position.pieces_of(Piece::Pawn(color)));
+        let shelter = shelter.count_ones() as i32;
+        value += (weights.king_shelter * shelter + weights.king_attack * zone_attacks) * phase / OPENING_PHASE;
+
+        value + self.pawn_structure(position, color)
+    }
+
+    /// Return the value of the pawn structure of one colour for that colour.
+    fn pawn_structure(&self, position: &Position, color: Color) -> i32 {
+        let pawns = position.pieces_of(Piece::Pawn(color));
+        let enemy_pawns = position.pieces_of(Piece::Pawn(color.opposite()));
+        let mut value = 0;
+        for (i, &file) in File::ALL.iter().enumerate() {
+            let on_file = (pawns & file_squares(file)).count_ones() as i32;
+            if on_file == 0 {
+                continue;
+            }
+            value += self.weights.doubled_pawn * (on_file - 1);
+            let neighbours = [i.wrapping_sub(1), i + 1].iter()
+                .filter_map(|&i| File::from_index(i))
+                .fold(0, |squares, file| squares | file_squares(file));
+            if pawns & neighbours == 0 {
+                value += self.weights.isolated_pawn * on_file;
+            }
+        }
+        for square in bitboard::squares(pawns) {
+            if front_span(color, square) & enemy_pawns == 0 {
+                value += self.weights.passed_pawn * relative_rank(color, square) as i32;
+            }
+        }
+        value
+    }
+}
+
+impl Evaluator for DefaultEvaluator {
+    fn evaluate(&self, position: &Position) -> i32 {
+        let phase = phase(position);
+        let white = self.evaluate_side(position, Color::White, phase)
+            - self.evaluate_side(position, Color::Black, phase);
+        if position.active_color() == Color::White {white} else {-white}
+    }
+}
+
+/// Return how far the game is from the endgame by the pieces left, from
+/// `OPENING_PHASE` with all pieces on the board down to 0 with only kings and pawns.
+fn phase(position: &Position) -> i32 {
+    let count = |piece: fn(Color) -> Piece| {
+        (position.pieces_of(piece(Color::White)) | position.pieces_of(piece(Color::Black))).count_ones() as i32
+    };
+    let phase = count(Piece::Knight) + count(Piece::Bishop) + 2 * count(Piece::Rook) + 4 * count(Piece::Queen);
+    phase.min(OPENING_PHASE)
+}
+
+/// Return the piece-square table value of the piece on the square, the
+/// king's blended between middlegame and endgame by `phase`.
+fn piece_square(piece: Piece, square: Square, phase: i32) -> i32 {
+    // the tables start at a8 for White, Black sees them mirrored
+    let rank = relative_rank(piece.color(), square);
+    let index = (7 - rank) * 8 + square.file().index();
+    match piece {
+        Piece::King(_) => (KING_MIDDLEGAME[index] * phase + KING_ENDGAME[index] * (OPENING_PHASE - phase)) / OPENING_PHASE,
+        _ => PIECE_SQUARES[crate::position::kind_index(piece) - 1][index]
+    }
+}
+
+/// Return the rank of the square counted from the side of the given colour, 0 to 7.
+fn relative_rank(color: Color, square: Square) -> usize {
+    match color {
+        Color::White => square.rank().index(),
+        Color::Black => 7 - square.rank().index()
+    }
+}
+
+/// Return the squares of the file.
+fn file_squares(file: File) -> Bitboard {
+    0x0101_0101_0101_0101 << file.index()
+}
+
+/// Return the squares in front of a pawn of the given colour on its file
+/// and the files next to it, where pawns of the other side could stop it.
+fn front_span(color: Color, square: Square) -> Bitboard {
+    let rank = square.rank().index();
+    let ahead = Rank::ALL.iter()
+        .filter(|other| if color == Color::White {other.index() > rank} else {other.index() < rank})
+        .fold(0, |squares, &other| squares | 0xFF << (8 * other.index()));
+    let file = square.file().index();
+    let files = [file.wrapping_sub(1), file, file + 1].iter()
+        .filter_map(|&file| File::from_index(file))
+        .fold(0, |squares, file| squares | file_squares(file));
+    ahead & files
+}
+
+/// Return the two squares in front of the king and the squares next to
+/// them, where pawns shelter it.
+fn shelter_squares(color: Color, king: Square) -> Bitboard {
+    let forward = if color == Color::White {1} else {-1};
+    (-1..=1)
+        .flat_map(|files| (1..=2).filter_map(move |ranks| king.offset(files, ranks * forward)))
+        .fold(0, |squares, square| squares | bitboard::bit(square))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DefaultEvaluator, Evaluator, Weights, WeightsError};
+    use crate::Game;
+
+    fn evaluate(fen: &str) -> i32 {
+        DefaultEvaluator::new().evaluate(Game::from_fen(fen).unwrap().position())
+    }
+
+    #[test]
+    fn equal_positions_are_even() {
+        assert_eq!(DefaultEvaluator::new().evaluate(Game::new().position()), 0);
+        // the same position with the colours swapped is worth the same to the side to move
+        let white = evaluate("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4");
+        let black = evaluate("rnbqk2r/pppp1ppp/5n2/2b1p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R b KQkq - 4 4");
+        assert_eq!(white, black);
+    }
+
+    #[test]
+    fn material_counts_for_the_side_to_move() {
+        let white = evaluate("4k3/8/8/8/8/8/8/3QK3 w - - 0 1");
+        assert!(white > 800, "{}", white);
+        assert_eq!(evaluate("4k3/8/8/8/8/8/8/3QK3 b - - 0 1"), -white);
+    }
+
+    #[test]
+    fn pawn_structure_counts() {
+        let healthy = evaluate("4k3/pp6/8/8/8/8/PP6/4K3 w - - 0 1");
+        let doubled = evaluate("4k3/pp6/8/8/8/1P6/1P6/4K3 w - - 0 1");
+        let passed = evaluate("4k3/p7/8/8/8/8/P6P/4K3 w - - 0 1");
+        assert!(doubled < healthy, "{} {}", doubled, healthy);
+        assert!(passed > 0, "{}", passed);
+    }
+
+    #[test]
+    fn weights_are_read_from_config() {
+        let weights: Weights = "# heavier pawns\npawn = 120\n\n  mobility=0 # no mobility\n".parse().unwrap();
+        assert_eq!(weights, Weights {pawn: 120, mobility: 0, ..Weights::default()});
+        assert_eq!(weights.to_string().parse(), Ok(weights));
+
+        assert_eq!("pawn 120".parse::<Weights>(), Err(WeightsError::InvalidLine(1)));
+        assert_eq!("\nrook = five".parse::<Weights>(), Err(WeightsError::InvalidValue(2, "five".to_string())));
+        assert_eq!("tempo = 10".parse::<Weights>(), Err(WeightsError::UnknownWeight(1, "tempo".to_string())));
+    }
+
+    #[test]
+    fn weights_are_loaded_from_files() {
+        let path = std::env::temp_dir().join(format!("ellmar-chess-weights-{}.txt", std::process::id()));
+        std::fs::write(&path, "queen = 1000\n").unwrap();
+        let weights = Weights::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(weights.unwrap().queen, 1000);
+        assert_eq!(Weights::load(&path), Err(WeightsError::Io(std::io::ErrorKind::NotFound)));
+    }
+
+    #[test]
+    fn weights_change_the_evaluation() {
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        let heavy = DefaultEvaluator::with_weights(Weights {queen: 2000, ..Weights::default()});
+        assert!(heavy.evaluate(game.position()) > DefaultEvaluator::new().evaluate(game.position()) + 1000);
+    }
+}
@@ -0,0 +1,306 @@
+use std::fmt;
+
+use crate::{Color, File, Game, Piece, Rank, Square};
+use crate::position::{CastlingRights, Position};
+
+/// Why a FEN string could not be read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FenError {
+    /// The string does not consist of exactly six fields separated by spaces.
+    WrongFieldCount,
+    /// The piece placement is malformed, e.g. a rank with nine squares.
+    InvalidPlacement,
+    /// The side to move is not "w" or "b".
+    InvalidActiveColor,
+    /// The castling field is malformed or does not match the kings and rooks.
+    InvalidCastling,
+    /// The en passant square is malformed or no pawn can have skipped it.
+    InvalidEnPassant,
+    /// The halfmove clock is not a number.
+    InvalidHalfmoveClock,
+    /// The fullmove number is not a number of at least 1.
+    InvalidFullmoveNumber,
+    /// The position cannot occur in a game, e.g. a side without a king,
+    /// pawns on the first or last rank or the side not to move in check.
+    IllegalPosition
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            FenError::WrongFieldCount => "a FEN string needs six fields",
+            FenError::InvalidPlacement => "invalid piece placement",
+            FenError::InvalidActiveColor => "invalid side to move",
+            FenError::InvalidCastling => "invalid castling rights",
+            FenError::InvalidEnPassant => "invalid en passant square",
+            FenError::InvalidHalfmoveClock => "invalid halfmove clock",
+            FenError::InvalidFullmoveNumber => "invalid fullmove number",
+            FenError::IllegalPosition => "the position is not legal"
+        };
+        write!(f, "{}", message)
+    }
+}
+
+impl std::error::Error for FenError {}
+
+impl Game {
+    /// Set up a game from a position in Forsyth-Edwards Notation, e.g.
+    /// `rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1`.
+    pub fn from_fen(fen: &str) -> Result<Game, FenError> {
+        let fields: Vec<&str> = fen.split(' ').collect();
+        if fields.len() != 6 {
+            return Err(FenError::WrongFieldCount);
+        }
+
+        let mut position = Position::empty();
+        read_placement(&mut position, fields[0])?;
+
+        position.active = match fields[1] {
+            "w" => Color::White,
+            "b" => Color::Black,
+            _ => return Err(FenError::InvalidActiveColor)
+        };
+        position.castling = read_castling(&position, fields[2])?;
+        position.en_passant = read_en_passant(&position, fields[3])?;
+        position.halfmove_clock = fields[4].parse().map_err(|_| FenError::InvalidHalfmoveClock)?;
+        position.fullmove_number = match fields[5].parse() {
+            Ok(number) if number >= 1 => number,
+            _ => return Err(FenError::InvalidFullmoveNumber)
+        };
+
+        check_legal(&position)?;
+        Ok(Game::from_position(position))
+    }
+
+    /// Return the position in Forsyth-Edwards Notation.
+    pub fn to_fen(&self) -> String {
+        let position = &self.position;
+        let mut fen = String::new();
+
+        for rank in Rank::ALL.iter().rev() {
+            let mut empty = 0;
+            for file in File::ALL {
+                match position.get_piece(Square::new(file, *rank)) {
+                    Some(piece) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        fen.push(piece.to_char());
+                    },
+                    None => empty += 1
+                }
+            }
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if *rank != Rank::First {
+                fen.push('/');
+            }
+        }
+
+        fen.push_str(if position.active == Color::White {" w "} else {" b "});
+
+        let rights = [
+            (position.castling.white_king_side, 'K'), (position.castling.white_queen_side, 'Q'),
+            (position.castling.black_king_side, 'k'), (position.castling.black_queen_side, 'q')
+        ];
+        let castling: String = rights.iter().filter(|(right, _)| *right).map(|(_, c)| c).collect();
+        fen.push_str(if castling.is_empty() {"-"} else {&castling});
+
+        match position.en_passant {
+            Some(square) => fen.push_str(&format!(" {}", square)),
+            None => fen.push_str(" -")
+        }
+        fen.push_str(&format!(" {} {}", position.halfmove_clock, position.fullmove_number));
+        fen
+    }
+}
+
+/// Put the pieces of the placement field on the empty board of `position`.
+fn read_placement(position: &mut Position, placement: &str) -> Result<(), FenError> {
+    let ranks: Vec<&str> = placement.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(FenError::InvalidPlacement);
+    }
+
+    // the placement starts with the eighth rank
+    for (rank, pieces) in Rank::ALL.iter().rev().zip(ranks) {
+        let mut file = 0;
+        let mut last_was_digit = false;
+        for c in pieces.chars() {
+            if let Some(empty) = c.to_digit(10) {
+                if last_was_digit || !(1..=8).contains(&empty) {
+                    return Err(FenError::InvalidPlacement);
+                }
+                file += empty as usize;
+                last_was_digit = true;
+            }
+            else {
+                let piece = Piece::from_char(c).ok_or(FenError::InvalidPlacement)?;
+                let file_of_piece = File::from_index(file).ok_or(FenError::InvalidPlacement)?;
+                position.set_piece(Square::new(file_of_piece, *rank), Some(piece));
+                file += 1;
+                last_was_digit = false;
+            }
+        }
+        if file != 8 {
+            return Err(FenError::InvalidPlacement);
+        }
+    }
+    Ok(())
+}
+
+/// Read the castling field, which must only allow castling
+/// with kings and rooks that are still on their starting squares.
+fn read_castling(position: &Position, castling: &str) -> Result<CastlingRights, FenError> {
+    let mut rights = CastlingRights {
+        white_king_side: false,
+        white_queen_side: false,
+        black_king_side: false,
+        black_queen_side: false
+    };
+    if castling == "-" {
+        return Ok(rights);
+    }
+
+    let mut last = None;
+    for c in castling.chars() {
+        let (right, color, rook_file) = match c {
+            'K' => (&mut rights.white_king_side, Color::White, File::H),
+            'Q' => (&mut rights.white_queen_side, Color::White, File::A),
+            'k' => (&mut rights.black_king_side, Color::Black, File::H),
+            'q' => (&mut rights.black_queen_side, Color::Black, File::A),
+            _ => return Err(FenError::InvalidCastling)
+        };
+        // the rights must be given once each, in the order KQkq
+        if last.is_some_and(|last| "KQkq".find(last) >= "KQkq".find(c)) {
+            return Err(FenError::InvalidCastling);
+        }
+        last = Some(c);
+
+        let rank = if color == Color::White {Rank::First} else {Rank::Eighth};
+        if position.get_piece(Square::new(File::E, rank)) != Some(Piece::King(color))
+            || position.get_piece(Square::new(rook_file, rank)) != Some(Piece::Rook(color)) {
+            return Err(FenError::InvalidCastling);
+        }
+        *right = true;
+    }
+    Ok(rights)
+}
+
+/// Read the en passant field, the square must lie behind a pawn
+/// of the side that just moved that can have got there with a double step.
+fn read_en_passant(position: &Position, en_passant: &str) -> Result<Option<Square>, FenError> {
+    if en_passant == "-" {
+        return Ok(None);
+    }
+
+    let square: Square = en_passant.parse().map_err(|_| FenError::InvalidEnPassant)?;
+    let moved = position.active.opposite();
+    let (rank, forward) = if moved == Color::White {(Rank::Third, 1)} else {(Rank::Sixth, -1)};
+    let pawn_square = square.offset(0, forward);
+    let start_square = square.offset(0, -forward);
+
+    if square.rank() != rank
+        || position.get_piece(square).is_some()
+        || start_square.is_some_and(|start| position.get_piece(start).is_some())
+        || pawn_square.and_then(|pawn| position.get_piece(pawn)) != Some(Piece::Pawn(moved)) {
+        return Err(FenError::InvalidEnPassant);
+    }
+    Ok(Some(square))
+}
+
+/// Check that the placement can occur in a game: each side has exactly one
+/// king, no pawn stands on the first or last rank and the side not to move
+/// is not in check. That the castling rights and the en passant square fit
+/// the placement is checked before, by `read_castling` and `read_en_passant`.
+fn check_legal(position: &Position) -> Result<(), FenError> {
+    for color in [Color::White, Color::Black] {
+        let kings = Square::all().filter(|&square| position.get_piece(square) == Some(Piece::King(color))).count();
+        if kings != 1 {
+            return Err(FenError::IllegalPosition);
+        }
+    }
+
+    let pawn_on_back_rank = Square::all()
+        .filter(|square| square.rank() == Rank::First || square.rank() == Rank::Eighth)
+        .any(|square| matches!(position.get_piece(square), Some(Piece::Pawn(_))));
+    if pawn_on_back_rank || position.in_check(position.active.opposite()) {
+        return Err(FenError::IllegalPosition);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FenError;
+    use crate::{Color, Game, Termination, GameState};
+
+    const POSITIONS: [&str; 5] = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10"
+    ];
+
+    #[test]
+    fn fen_round_trips() {
+        for fen in POSITIONS {
+            assert_eq!(Game::from_fen(fen).unwrap().to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn fen_fields_are_read() {
+        let game = Game::from_fen(POSITIONS[1]).unwrap();
+        assert_eq!(game.active_color(), Color::Black);
+        assert_eq!(game.position.en_passant, Some("e3".parse().unwrap()));
+        assert!(game.position.castling.white_king_side && game.position.castling.black_queen_side);
+    }
+
+    #[test]
+    fn fen_follows_moves() {
+        let mut game = Game::from_fen(POSITIONS[0]).unwrap();
+        game.make_move("E2".to_string(), "E4".to_string()).unwrap();
+        assert_eq!(game.to_fen(), POSITIONS[1]);
+        game.make_move("G8".to_string(), "F6".to_string()).unwrap();
+        game.make_move("G1".to_string(), "F3".to_string()).unwrap();
+        assert_eq!(game.to_fen(), "rnbqkb1r/pppppppp/5n2/8/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 2 2");
+    }
+
+    #[test]
+    fn fen_sets_game_state() {
+        let mate = Game::from_fen("7k/6Q1/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        let expected = GameState::GameOver {winner: Some(Color::White), reason: Termination::Checkmate};
+        assert_eq!(mate.get_game_state(), expected);
+    }
+
+    #[test]
+    fn invalid_fen_is_rejected() {
+        let invalid = [
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0", FenError::WrongFieldCount),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR  w KQkq - 0 1", FenError::WrongFieldCount),
+            ("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenError::InvalidPlacement),
+            ("rnbqkbnr/pppppppp/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenError::InvalidPlacement),
+            ("rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenError::InvalidPlacement),
+            ("rnbqkbnr/pppppppp/44/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenError::InvalidPlacement),
+            ("rnbqkbnr/ppppxppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenError::InvalidPlacement),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1", FenError::InvalidActiveColor),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN1 w KQkq - 0 1", FenError::InvalidCastling),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w QKkq - 0 1", FenError::InvalidCastling),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkqX - 0 1", FenError::InvalidCastling),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e3 0 1", FenError::InvalidEnPassant),
+            ("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e6 0 1", FenError::InvalidEnPassant),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - x 1", FenError::InvalidHalfmoveClock),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 0", FenError::InvalidFullmoveNumber),
+            ("rnbq1bnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQ - 0 1", FenError::IllegalPosition),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNP w Qkq - 0 1", FenError::IllegalPosition),
+            ("4k3/8/8/8/8/8/4R3/4K3 w - - 0 1", FenError::IllegalPosition)
+        ];
+        for (fen, error) in invalid {
+            assert_eq!(Game::from_fen(fen).err(), Some(error), "{}", fen);
+        }
+    }
+}
@@ -1,10 +1,61 @@
 use std::fmt;
+use std::str::FromStr;
+
+mod bitboard;
+mod eval;
+mod fen;
+mod movelist;
+mod outcome;
+mod perft;
+mod pgn;
+mod position;
+mod san;
+mod search;
+mod square;
+mod transposition;
+mod uci;
+mod zobrist;
+
+use position::Undo;
+
+pub use eval::{DefaultEvaluator, Evaluator, Weights, WeightsError};
+pub use fen::FenError;
+pub use movelist::{MoveList, MoveListIter};
+pub use outcome::{Outcome, Termination};
+pub use pgn::{PgnError, PgnErrorKind, PgnGame};
+pub use position::Position;
+pub use san::SanError;
+pub use search::{Limit, Score, SearchResult, Searcher, MAX_HASH_MEGABYTES};
+pub use square::{File, Rank, Square, ParseSquareError};
+pub use uci::UciError;
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum GameState {
     InProgress,
     Check,
-    GameOver
+    GameOver {
+        winner: Option<Color>,
+        reason: Termination
+    }
+}
+
+/// Why a move or another action on the game could not be made.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MoveError {
+    /// A tile could not be read, e.g. "I9".
+    InvalidSquare(String),
+    /// There is no piece on the tile to move from.
+    NoPiece,
+    /// The piece belongs to the colour whose turn it is not.
+    WrongTurn,
+    /// The piece cannot move to the given tile.
+    IllegalMove,
+    /// The game is already over.
+    GameFinished,
+    /// The move promotes a pawn, but no valid promotion piece was given.
+    PromotionRequired,
+    /// A draw was accepted, but the other player has not offered one.
+    NoDrawOffer
 }
 
 /* IMPORTANT:
@@ -12,13 +63,13 @@ pub enum GameState {
  * - Write well structured and clean code!
  */
 
- #[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Color {
     White,
     Black
 }
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Piece {
     King(Color),
     Queen(Color),
@@ -28,14 +79,203 @@ pub enum Piece {
     Pawn(Color)
 }
 
-#[derive(Copy, Clone)]
+#[derive(Clone)]
 pub struct Game {
     /* save board, active colour, ... */
     state: GameState,
-    board: [[Option<Piece>; 8]; 8],
-    active: Color
+    position: Position,
+    /// The piece a pawn reaching the last rank is promoted to.
+    promotion: Promotion,
+    /// Every move played so far, the last move at the end.
+    history: Vec<HistoryEntry>,
+    /// Moves taken back with `undo_move`, the most recently taken back at the end.
+    redo: Vec<Move>,
+    /// The player who offered a draw that has not been accepted or declined yet.
+    draw_offer: Option<Color>,
+    /// The position before the first move and after every move, reduced to
+    /// what counts for repetitions, the current position at the end.
+    positions: Vec<Position>
+}
+
+/// A move in the history of a game, with what it changed so it can be taken back.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    mv: Move,
+    undo: Undo,
+    /// The state of the game before the move.
+    state: GameState,
+    /// The draw offer standing before the move, which the move may have declined.
+    draw_offer: Option<Color>
+}
+
+/// The pieces a pawn can be promoted to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Promotion {
+    Queen,
+    Rook,
+    Bishop,
+    Knight
+}
+
+/// A move of a piece from one square to another. Castling is given as the
+/// king moving two squares towards the rook, en passant as the pawn moving
+/// to the square behind the captured pawn.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Move {
+    /// The square moved from in bits 0 to 5, the square moved to in bits
+    /// 6 to 11 and the promotion in bits 12 to 14, 0 for none and 1 + its
+    /// index in `Promotion::ALL` otherwise.
+    bits: u16
+}
+
+/// Error returned when a move cannot be read from a string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseMoveError;
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoveError::InvalidSquare(square) => write!(f, "\"{}\" is not a square on the board", square),
+            MoveError::NoPiece => write!(f, "there is no piece on that square"),
+            MoveError::WrongTurn => write!(f, "it is the other player's turn"),
+            MoveError::IllegalMove => write!(f, "the piece cannot move there"),
+            MoveError::GameFinished => write!(f, "the game is over"),
+            MoveError::PromotionRequired => write!(f, "choose a piece to promote the pawn to"),
+            MoveError::NoDrawOffer => write!(f, "no draw has been offered")
+        }
+    }
 }
 
+impl std::error::Error for MoveError {}
+
+impl Color {
+    /// Return the other colour.
+    pub fn opposite(&self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White
+        }
+    }
+}
+
+impl Promotion {
+    /// All pieces a pawn can be promoted to.
+    pub const ALL: [Promotion; 4] = [Promotion::Queen, Promotion::Rook, Promotion::Bishop, Promotion::Knight];
+
+    /// Parse a promotion piece from its name, e.g. "queen", or its letter, e.g. "q".
+    pub fn from_name(name: &str) -> Option<Promotion> {
+        match name.to_lowercase().as_str() {
+            "queen" | "q" => Some(Promotion::Queen),
+            "rook" | "r" => Some(Promotion::Rook),
+            "bishop" | "b" => Some(Promotion::Bishop),
+            "knight" | "n" => Some(Promotion::Knight),
+            _ => None
+        }
+    }
+
+    /// Return the lower case letter of the piece, as used in `e7e8q`.
+    pub fn to_char(self) -> char {
+        match self {
+            Promotion::Queen => 'q',
+            Promotion::Rook => 'r',
+            Promotion::Bishop => 'b',
+            Promotion::Knight => 'n'
+        }
+    }
+
+    /// Return the piece of the given colour.
+    pub fn to_piece(self, color: Color) -> Piece {
+        match self {
+            Promotion::Queen => Piece::Queen(color),
+            Promotion::Rook => Piece::Rook(color),
+            Promotion::Bishop => Piece::Bishop(color),
+            Promotion::Knight => Piece::Knight(color)
+        }
+    }
+}
+
+impl Move {
+    /// A move that is not a promotion.
+    pub fn new(from: Square, to: Square) -> Move {
+        Move::encode(from, to, None)
+    }
+
+    /// A pawn move to the last rank, promoting to `promotion`.
+    pub fn with_promotion(from: Square, to: Square, promotion: Promotion) -> Move {
+        Move::encode(from, to, Some(promotion))
+    }
+
+    fn encode(from: Square, to: Square, promotion: Option<Promotion>) -> Move {
+        let promotion = promotion.map_or(0, |promotion| {
+            1 + Promotion::ALL.iter().position(|&other| other == promotion).expect("all promotions are listed")
+        });
+        Move {bits: (from.index() | to.index() << 6 | promotion << 12) as u16}
+    }
+
+    pub fn from(&self) -> Square {
+        square_of(self.bits)
+    }
+
+    pub fn to(&self) -> Square {
+        square_of(self.bits >> 6)
+    }
+
+    pub fn promotion(&self) -> Option<Promotion> {
+        match (self.bits >> 12) as usize {
+            0 => None,
+            promotion => Some(Promotion::ALL[promotion - 1])
+        }
+    }
+}
+
+impl fmt::Debug for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Move")
+            .field("from", &self.from())
+            .field("to", &self.to())
+            .field("promotion", &self.promotion())
+            .finish()
+    }
+}
+
+impl fmt::Display for Move {
+    /// Write the move as the two squares followed by the promotion, e.g. `e7e8q`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.from(), self.to())?;
+        if let Some(promotion) = self.promotion() {
+            write!(f, "{}", promotion.to_char())?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Move {
+    type Err = ParseMoveError;
+
+    /// Read a move written as the two squares followed by an optional
+    /// promotion letter, e.g. `e2e4` or `e7e8q`.
+    fn from_str(s: &str) -> Result<Move, ParseMoveError> {
+        let from = s.get(0..2).and_then(|from| from.parse().ok()).ok_or(ParseMoveError)?;
+        let to = s.get(2..4).and_then(|to| to.parse().ok()).ok_or(ParseMoveError)?;
+        match s.get(4..) {
+            Some("") => Ok(Move::new(from, to)),
+            Some(promotion) if promotion.len() == 1 => {
+                let promotion = Promotion::from_name(promotion).ok_or(ParseMoveError)?;
+                Ok(Move::with_promotion(from, to, promotion))
+            },
+            _ => Err(ParseMoveError)
+        }
+    }
+}
+
+impl fmt::Display for ParseMoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "not a move such as e2e4 or e7e8q")
+    }
+}
+
+impl std::error::Error for ParseMoveError {}
+
 impl Piece {
     fn icon (&self) -> String {
         match self {
@@ -55,17 +295,56 @@ impl Piece {
         }.to_owned()
     }
 
-    fn iswhite (&self) -> bool {
-        match &self {
-            Piece::Bishop(Color::White) => return true,
-            Piece::Pawn(Color::White) => return true,
-            Piece::King(Color::White) => return true,
-            Piece::Queen(Color::White) => return true,
-            Piece::Rook(Color::White) => return true,
-            Piece::Knight(Color::White) => return true,
-            _ => return false
+    /// Read a piece from its FEN letter, upper case for white, e.g. 'N' or 'k'.
+    pub fn from_char(c: char) -> Option<Piece> {
+        let color = if c.is_ascii_uppercase() {Color::White} else {Color::Black};
+        match c.to_ascii_lowercase() {
+            'k' => Some(Piece::King(color)),
+            'q' => Some(Piece::Queen(color)),
+            'r' => Some(Piece::Rook(color)),
+            'n' => Some(Piece::Knight(color)),
+            'b' => Some(Piece::Bishop(color)),
+            'p' => Some(Piece::Pawn(color)),
+            _ => None
+        }
+    }
+
+    /// Return the FEN letter of the piece, upper case for white.
+    pub fn to_char(&self) -> char {
+        let c = match self {
+            Piece::King(_) => 'k',
+            Piece::Queen(_) => 'q',
+            Piece::Rook(_) => 'r',
+            Piece::Knight(_) => 'n',
+            Piece::Bishop(_) => 'b',
+            Piece::Pawn(_) => 'p'
+        };
+        if self.iswhite() {c.to_ascii_uppercase()} else {c}
+    }
+
+    /// Return the usual value of the piece in centipawns, a pawn being
+    /// worth 100. The king, which is never exchanged, is worth 0.
+    pub fn value(&self) -> i32 {
+        eval::DEFAULT_WEIGHTS.value(*self)
+    }
+
+    /// Return the colour of the piece.
+    pub fn color (&self) -> Color {
+        match *self {
+            Piece::King(color) | Piece::Queen(color) | Piece::Rook(color) |
+            Piece::Knight(color) | Piece::Bishop(color) | Piece::Pawn(color) => color
         }
     }
+
+    fn iswhite (&self) -> bool {
+        self.color() == Color::White
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::new()
+    }
 }
 
 impl Game {
@@ -74,474 +353,294 @@ impl Game {
         Game {
             /* initialise board, set active colour to white, ... */
             state: GameState::InProgress,
-            active: Color::White,
-            board: [
-                [
-                    Some(Piece::Rook(Color::Black)),
-                    Some(Piece::Knight(Color::Black)),
-                    Some(Piece::Bishop(Color::Black)),
-                    Some(Piece::King(Color::Black)),
-                    Some(Piece::Queen(Color::Black)),
-                    Some(Piece::Bishop(Color::Black)),
-                    Some(Piece::Knight(Color::Black)),
-                    Some(Piece::Rook(Color::Black))
-                ],
-                [Some(Piece::Pawn(Color::Black)); 8],
-                [None; 8], [None; 8], [None; 8], [None; 8],
-//                [None; 8], //for testing
-                [Some(Piece::Pawn(Color::White)); 8],
-                [
-                    Some(Piece::Rook(Color::White)),
-                    Some(Piece::Knight(Color::White)),
-                    Some(Piece::Bishop(Color::White)),
-                    Some(Piece::King(Color::White)),
-                    Some(Piece::Queen(Color::White)),
-                    Some(Piece::Bishop(Color::White)),
-                    Some(Piece::Knight(Color::White)),
-                    Some(Piece::Rook(Color::White))
-                ]
-            ]
-        }
-    }
-    
-    /// Return the piece at the position, if there is one
-    fn get_piece(&self, coords: [usize; 2]) -> Option<&Piece> {
-        let piece = self.board[coords[1]][coords[0]].as_ref();
-            if piece.is_none() {return None;} else {return Some(piece.unwrap());}
-    }
-
-    /// If the current game state is InProgress and the move is legal, 
-    /// move a piece and return the resulting state of the game.
-    pub fn make_move(&mut self, _from: String, _to: String) -> Option<GameState> {
-        if self.state != GameState::InProgress {
-            return None;
-        }
-        
-        let coords_from = pos_from_string(&_from);
-        let coords_to = pos_from_string(&_to);
-        let self_copy = self.to_owned();
-        let piece_from = self_copy.get_piece(coords_from);
-
-        if piece_from.is_none() {
-            return None;
-        }
-        if piece_from.unwrap().iswhite() && self.active == Color::White {
-            return None;
-        }
-        
-        let possible_moves = self.get_possible_moves(_from);
-        if possible_moves.is_none() {
-            return None;
+            position: Position::start(),
+            promotion: Promotion::Queen,
+            history: vec!(),
+            redo: vec!(),
+            draw_offer: None,
+            positions: vec!(Position::start().repetition_key())
         }
+    }
 
-        for _move in possible_moves.clone().unwrap() {
-            println!("{}", _move);
-        }
+    /// Start a game from the given position, which must be legal.
+    pub(crate) fn from_position(position: Position) -> Game {
+        let mut game = Game {position, positions: vec!(position.repetition_key()), ..Game::new()};
+        game.update_state(position.active);
+        game
+    }
 
-        // to is to string to avoid lowe/uppercase incompatability
-        if possible_moves.unwrap().contains(&pos_to_string(coords_to)) {
-            // Should it really be [1], [0]??
-            self.board[coords_from[1]][coords_from[0]] = None;
-            self.board[coords_to[1]][coords_to[0]] = Some(piece_from.unwrap().to_owned());
-        }
-        else {
-            return None;
-        }
+    /// Return the current position.
+    pub fn position(&self) -> &Position {
+        &self.position
+    }
 
-        
-        return Some(self.state);
+    /// Return the piece on the square, if there is one.
+    pub fn piece_at(&self, square: Square) -> Option<Piece> {
+        self.position.get_piece(square)
     }
 
-    /// Set the piece type that a peasant becames following a promotion.
-    pub fn set_promotion(&mut self, _piece: String) -> () {
-        ()
+    /// If the game is not over and the move is legal,
+    /// move a piece and return the resulting state of the game,
+    /// otherwise return why the move could not be made.
+    /// A pawn reaching the last rank is promoted to the piece chosen
+    /// with `set_promotion`, a queen unless anything else was chosen.
+    pub fn make_move(&mut self, _from: String, _to: String) -> Result<GameState, MoveError> {
+        let from = parse_square(&_from)?;
+        let to = parse_square(&_to)?;
+        self.play(Move::with_promotion(from, to, self.promotion))
     }
 
-    /// Get the current game state.
-    pub fn get_game_state(&self) -> GameState {
-        self.state
+    /// Like `make_move`, but with the promotion piece given along with the move,
+    /// e.g. `"E7"`, `"E8"`, `"q"` as in UCI `e7e8q`. The piece is given by its
+    /// name or letter and ignored if the move is not a promotion.
+    pub fn make_move_with_promotion(&mut self, _from: String, _to: String, _piece: String) -> Result<GameState, MoveError> {
+        let from = parse_square(&_from)?;
+        let to = parse_square(&_to)?;
+        self.play(Move::encode(from, to, Promotion::from_name(&_piece)))
     }
-    
-    /// If a piece is standing on the given tile, return all possible 
-    /// new positions of that piece. Don't forget to the rules for check. 
-    /// 
-    /// (optional) Don't forget to include en passent and castling.
-    pub fn get_possible_moves(&self, _postion: String) -> Option<Vec<String>> {
-        let piece = self.get_piece(pos_from_string(&_postion));
-        if piece.is_none() {
-            return None
+
+    /// If the game is not over and the move is legal, make the move and
+    /// return the resulting state of the game. The promotion of the move
+    /// is ignored if it is not a promotion.
+    pub fn play(&mut self, mv: Move) -> Result<GameState, MoveError> {
+        if matches!(self.state, GameState::GameOver { .. }) {
+            return Err(MoveError::GameFinished);
         }
-        else {
-            match piece.unwrap() {
-                Piece::Pawn(_) => return self.pawn_possible(&_postion),
-                Piece::Rook(_) => return self.rook_possible(&_postion),
-                Piece::Bishop(_) => return self.bishop_possible(&_postion),
-                Piece::Queen(_) => return self.queen_possible(&_postion),
-                Piece::King(_) => return self.king_possible(&_postion),
-                Piece::Knight(_) => return self.knight_possible(&_postion)
-            }
+
+        let piece_from = self.position.get_piece(mv.from()).ok_or(MoveError::NoPiece)?;
+        if piece_from.color() != self.position.active {
+            return Err(MoveError::WrongTurn);
         }
+
+        let legal_move = self.position.moves_from(mv.from())
+            .into_iter()
+            .find(|legal| legal.to() == mv.to())
+            .ok_or(MoveError::IllegalMove)?;
+        let promotion = match (legal_move.promotion(), mv.promotion()) {
+            (None, _) => None,
+            (Some(_), Some(promotion)) => Some(promotion),
+            (Some(_), None) => return Err(MoveError::PromotionRequired)
+        };
+
+        self.redo.clear();
+        self.push_move(Move::encode(mv.from(), mv.to(), promotion));
+        Ok(self.state)
     }
-    
-    fn knight_possible(&self, position: &String) -> Option<Vec<String>> {
-        let coords = pos_from_string(position);
-        let piece = self.get_piece(coords).unwrap();
-        let iswhite = piece.iswhite();
-
-        let mut coords_usize: [usize; 2];
-        let mut other_piece: Option<&Piece>;
-        let mut string_positions = vec!();
-        let possible_positions = [
-            [coords[0] as i8 +2, coords[1] as i8 +1], [coords[0] as i8 +2, coords[1] as i8 -1],
-            [coords[0] as i8 -2, coords[1] as i8 +1], [coords[0] as i8 -2, coords[1] as i8 -1],
-            [coords[0] as i8 +1, coords[1]as i8 +2], [coords[0] as i8 -1, coords[1] as i8 +2],
-            [coords[0] as i8 +1, coords[1] as i8 -2], [coords[0] as i8 -1, coords[1] as i8 -2]
-        ];
 
-        for new_coords in possible_positions {
-            if 0 <= new_coords[0] && new_coords[0] <= 7 &&
-            0 <= new_coords[1] && new_coords[1] <= 7 {
-                coords_usize = [new_coords[0] as usize, new_coords[1] as usize];
-                other_piece = self.get_piece(coords_usize);
-                if other_piece.is_none() {
-                    string_positions.push(pos_to_string(coords_usize));
-                }
-                else if other_piece.unwrap().iswhite() != iswhite {
-                    string_positions.push(pos_to_string(coords_usize));
-                }
-            }
+    /// Make a legal move and add it to the history.
+    fn push_move(&mut self, mv: Move) {
+        let draw_offer = self.draw_offer;
+        // moving instead of accepting declines the draw offered by the other player
+        if self.draw_offer == Some(self.position.active.opposite()) {
+            self.draw_offer = None;
         }
+        let undo = self.position.apply_move(mv.from(), mv.to(), mv.promotion().unwrap_or(self.promotion));
+        self.history.push(HistoryEntry {mv, undo, state: self.state, draw_offer});
+        self.positions.push(self.position.repetition_key());
+        self.update_state(self.position.active);
+    }
 
-        if string_positions.len() > 0 {
-            return Some(string_positions);
-        }
-        else {
+    /// Take back the last move and return it, None if no move has been made
+    /// or a player has ended the game, e.g. by resigning, which cannot be
+    /// taken back. The move can be made again with `redo_move`.
+    pub fn undo_move(&mut self) -> Option<Move> {
+        if self.ended_by_player() {
             return None;
         }
+        let entry = self.history.pop()?;
+        self.position.unapply_move(entry.mv.from(), entry.mv.to(), &entry.undo);
+        self.positions.pop();
+        self.state = entry.state;
+        self.draw_offer = entry.draw_offer;
+        self.redo.push(entry.mv);
+        Some(entry.mv)
     }
 
-    fn king_possible(&self, position: &String) -> Option<Vec<String>> {
-        let coords = pos_from_string(position);
-        let piece = self.get_piece(coords).unwrap();
-        let iswhite = piece.iswhite();
-        let mut other_piece: Option<&Piece>;
-        let mut string_positions = vec!();
-        let possible_positions = [
-            [coords[0]+1, coords[1]], [coords[0]+1, coords[1]+1], [coords[0], coords[1]+1], [coords[0]-1, coords[1]],
-            [coords[0]-1, coords[1]-1], [coords[0], coords[1]-1], [coords[0]+1, coords[1]-1], [coords[0]-1, coords[1]+1]
-        ];
-        for new_coords in possible_positions {
-            if 0 <= new_coords[0] && new_coords[0] <= 7 &&
-            0 <= new_coords[1] && new_coords[1] <= 7 {
-                other_piece = self.get_piece(new_coords);
-                if other_piece.is_none() {
-                    string_positions.push(pos_to_string(new_coords));
-                }
-                else if other_piece.unwrap().iswhite() != iswhite {
-                    string_positions.push(pos_to_string(new_coords));
-                }
-            }
-        }
-        if string_positions.len() > 0 {
-            return Some(string_positions);
-        }
-        else {
+    /// Make the move last taken back with `undo_move` again and return the
+    /// resulting state of the game, None if there is no such move or a
+    /// player has ended the game since. Playing any other move forgets the
+    /// moves that were taken back.
+    pub fn redo_move(&mut self) -> Option<GameState> {
+        if self.ended_by_player() {
             return None;
         }
+        let mv = self.redo.pop()?;
+        self.push_move(mv);
+        Some(self.state)
     }
 
-    fn queen_possible(&self, position: &String) -> Option<Vec<String>> {
-        // Queen can move as bishop and rook
-        let mut string_positions = vec!();
-        let bishop_moves = self.bishop_possible(position);
-        let rook_moves = self.rook_possible(position);
+    /// Return the moves played so far, the first move first.
+    pub fn history(&self) -> &[HistoryEntry] {
+        &self.history
+    }
 
-        if !bishop_moves.is_none() {
-            string_positions.append(&mut bishop_moves.unwrap());
-        }
-        if !rook_moves.is_none() {
-            string_positions.append(&mut rook_moves.unwrap())
-        }
-        if string_positions.len() > 0 {
-            return Some(string_positions);
-        }
-        else {
-            return None
-        }
-    }
-    
-    fn bishop_possible(&self, position: &String) -> Option<Vec<String>> {
-        let coords = pos_from_string(position);
-        let piece = self.get_piece(coords).unwrap();
-        let iswhite = piece.iswhite();
-        let mut string_positions = vec!();
-        let mut new_coords: [usize; 2];
-        let mut other_piece: Option<&Piece>;
-        let mut x: usize;
-        let mut y: usize;
-        
-        x = 0;
-        y = 0;
-        while x + coords[0] < 7 && y + coords[1] < 7{
-            x += 1;
-            y += 1;
-            new_coords = [coords[0]+x, coords[1]+y];
-            other_piece = self.get_piece(new_coords);
-            if !other_piece.is_none() {
-                if other_piece.unwrap().iswhite() != iswhite {
-                    string_positions.push(pos_to_string(new_coords));
-                }
-                break;
-            }
-            else {
-                string_positions.push(pos_to_string(new_coords));
-            }
-        }
+    /// Return all legal moves of the piece on `square`, whichever colour it
+    /// has. A pawn reaching the last rank gives one move for every promotion.
+    pub fn moves_from(&self, square: Square) -> Vec<Move> {
+        self.position.moves_from(square).to_vec()
+    }
 
-        x = 0;
-        y = 0;
-        while coords[0] > 0 && coords[1] > 0 && coords[0] - x > 0 && coords[1] - y > 0 {
-            x += 1;
-            y += 1;
-            new_coords = [coords[0]-x, coords[1]-y];
-            other_piece = self.get_piece(new_coords);
-            if !other_piece.is_none() {
-                if other_piece.unwrap().iswhite() != iswhite {
-                    string_positions.push(pos_to_string(new_coords));
-                }
-                break;
-            }
-            else {
-                string_positions.push(pos_to_string(new_coords));
-            }
+    /// Set the piece type that a peasant becames following a promotion:
+    /// "queen", "rook", "bishop" or "knight". Unknown names are ignored.
+    pub fn set_promotion(&mut self, _piece: String) {
+        if let Some(promotion) = Promotion::from_name(&_piece) {
+            self.promotion = promotion;
         }
+    }
 
-        x = 0;
-        y = 0;
-        while coords[0] > 0 && coords[0] - x > 0 && y + coords[1] < 7{
-            x += 1;
-            y += 1;
-            new_coords = [coords[0]-x, coords[1]+y];
-            other_piece = self.get_piece(new_coords);
-            if !other_piece.is_none() {
-                if other_piece.unwrap().iswhite() != iswhite {
-                    string_positions.push(pos_to_string(new_coords));
-                }
-                break;
-            }
-            else {
-                string_positions.push(pos_to_string(new_coords));
-            }
-        }
+    /// Get the colour whose turn it is.
+    pub fn active_color(&self) -> Color {
+        self.position.active
+    }
 
-        x = 0;
-        y = 0;
-        while coords[1] > 0 && x + coords[0] < 7 && coords[1] - y > 0{
-            x += 1;
-            y += 1;
-            new_coords = [coords[0]+x, coords[1]-y];
-            other_piece = self.get_piece(new_coords);
-            if !other_piece.is_none() {
-                if other_piece.unwrap().iswhite() != iswhite {
-                    string_positions.push(pos_to_string(new_coords));
-                }
-                break;
-            }
-            else {
-                string_positions.push(pos_to_string(new_coords));
-            }
-        }
-        return if string_positions.len() > 0 {Some(string_positions)} else {None};
-    }
-
-    fn rook_possible(&self, position: &String) -> Option<Vec<String>> {
-        let coords = pos_from_string(position);
-        let piece = self.get_piece(coords).unwrap();
-        let iswhite = piece.iswhite();
-        let mut string_positions = vec!();
-        let mut idx: usize;
-        let mut new_coords: [usize; 2];
-        let mut other_piece: Option<&Piece>;
-        idx = 0;
-        while idx + coords[1] < 7 {
-            idx += 1;
-            new_coords = [coords[0], coords[1]+idx];
-            other_piece = self.get_piece(new_coords);
-            if !other_piece.is_none() {
-                if other_piece.unwrap().iswhite() != iswhite {
-                    string_positions.push(pos_to_string(new_coords));
-                }
-                break;
-            }
-            else {
-                string_positions.push(pos_to_string(new_coords));
-            }
-        }
+    /// Get the current game state.
+    pub fn get_game_state(&self) -> GameState {
+        self.state
+    }
 
-        idx = 0;
-        while coords[1] > 0 && coords[1]-idx > 0 {
-            idx += 1;
-            new_coords = [coords[0], coords[1]-idx];
-            other_piece = self.get_piece(new_coords);
-            if !other_piece.is_none() {
-                if other_piece.unwrap().iswhite() != iswhite {
-                    string_positions.push(pos_to_string(new_coords));
-                }
-                break;
-            }
-            else {
-                string_positions.push(pos_to_string(new_coords));
-            }
-        }
+    /// Recalculate the game state from the point of view of `to_move`,
+    /// the colour that is about to make a move.
+    fn update_state(&mut self, to_move: Color) {
+        let check = self.position.in_check(to_move);
+        let draw = |reason| GameState::GameOver {winner: None, reason};
 
-        idx = 0;
-        while idx + coords[0] < 7 {
-            idx += 1;
-            new_coords = [coords[0]+idx, coords[1]];
-            other_piece = self.get_piece(new_coords);
-            if !other_piece.is_none() {
-                if other_piece.unwrap().iswhite() != iswhite {
-                    string_positions.push(pos_to_string(new_coords));
-                }
-                break;
+        self.state = if self.position.has_legal_move(to_move) {
+            if self.position.insufficient_material() {
+                draw(Termination::InsufficientMaterial)
             }
-            else {
-                string_positions.push(pos_to_string(new_coords));
+            else if self.position.halfmove_clock >= 150 {
+                draw(Termination::SeventyFiveMoveRule)
             }
-        }
-
-        idx = 0;
-        while coords[0] > 0 && coords[0]-idx > 0 {
-            idx += 1;
-            new_coords = [coords[0]-idx, coords[1]];
-            other_piece = self.get_piece(new_coords);
-            if !other_piece.is_none() {
-                if other_piece.unwrap().iswhite() != iswhite {
-                    string_positions.push(pos_to_string(new_coords));
-                }
-                break;
+            else if self.repetitions() >= 5 {
+                draw(Termination::FivefoldRepetition)
             }
-            else {
-                string_positions.push(pos_to_string(new_coords));
+            else if check {GameState::Check} else {GameState::InProgress}
+        }
+        else if check {
+            GameState::GameOver {
+                winner: Some(to_move.opposite()),
+                reason: Termination::Checkmate
             }
         }
-        return if string_positions.len() > 0 {Some(string_positions)} else {None};
+        else {
+            GameState::GameOver {
+                winner: None,
+                reason: Termination::Stalemate
+            }
+        };
     }
 
-    fn pawn_possible(&self, position: &String) -> Option<Vec<String>> {
-        let coords = pos_from_string(position);
-        let piece = self.get_piece(coords).unwrap();
-        let iswhite = piece.iswhite();
-        let mut string_positions = vec!();
-        let mut diagonal_moves = vec!();
-
-        // return None if piece at edge of board
-        if coords[1] == 0 || coords[1] == 7 {return None;}
+    /// Return how often the current position has occurred, counting itself.
+    fn repetitions(&self) -> usize {
+        // positions before the last capture or pawn move cannot come back
+        let current = self.positions.last().expect("the current position is recorded");
+        self.positions.iter().rev()
+            .take(self.position.halfmove_clock as usize + 1)
+            .filter(|&position| position == current)
+            .count()
+    }
 
-        // 1 tile forward
-        let mut possible_positions = if iswhite {
-            vec!([coords[0], coords[1]-1])
-        }
-        else {
-            vec!([coords[0], coords[1]+1])
-        };
+    /// Return the number of half moves since the last capture or pawn move.
+    pub fn halfmove_clock(&self) -> u32 {
+        self.position.halfmove_clock
+    }
 
-        // 2 tiles forward
-        if iswhite && self.get_piece([coords[0], coords[1]-1]).is_none() {
-            possible_positions.push([coords[0], coords[1]-2]);
+    /// Return why the player to move may claim a draw, None if they may not:
+    /// after 50 moves by each side without a capture or pawn move, or when
+    /// the current position occurred for the third time.
+    pub fn claimable_draw(&self) -> Option<Termination> {
+        if matches!(self.state, GameState::GameOver { .. }) {
+            None
         }
-        else if !iswhite && self.get_piece([coords[0], coords[1]+1]).is_none() {
-            possible_positions.push([coords[0], coords[1]+2])
+        else if self.position.halfmove_clock >= 100 {
+            Some(Termination::FiftyMoveRule)
         }
-        
-        // remove occupied positions
-        for position in possible_positions {
-            if self.get_piece(position).is_none() {
-                string_positions.push(pos_to_string(position));
-            }
+        else if self.repetitions() >= 3 {
+            Some(Termination::ThreefoldRepetition)
         }
-
-        // diagonal moves if piece not at edge of board
-        if iswhite {
-            if coords[0] > 0 {
-                diagonal_moves.push([coords[0]-1, coords[1]-1]);
-            }
-            if coords[0] < 7 {
-                diagonal_moves.push([coords[0]+1, coords[1]-1]);
-            }
         else {
-            if coords[0] > 0 {
-                diagonal_moves.push([coords[0]+1, coords[1]+1]);
-            }
-            if coords[0] < 7 {
-                diagonal_moves.push([coords[0]+1, coords[1]+1]);
-            }
-        }
+            None
         }
-        // diagnonal move possible if tile occupied
+    }
+
+    /// End the game in a draw if it can be claimed, see `claimable_draw`,
+    /// and return the resulting state, None if no draw can be claimed.
+    pub fn claim_draw(&mut self) -> Option<GameState> {
+        let reason = self.claimable_draw()?;
+        self.state = GameState::GameOver {winner: None, reason};
+        Some(self.state)
+    }
 
-        // Is there a better way?
-        for pos in diagonal_moves {
-            if !self.get_piece(pos).is_none() {
-                if iswhite != self.get_piece(pos).unwrap().iswhite() {
-                    string_positions.push(pos_to_string(pos));
-                }
+    /// If a piece is standing on the given tile, return all possible
+    /// new positions of that piece. Moves that would leave the own king
+    /// in check, e.g. moving a pinned piece, are not included.
+    /// Castling is given as the king moving two tiles towards the rook,
+    /// en passant as the pawn moving to the tile behind the captured pawn.
+    pub fn get_possible_moves(&self, _postion: String) -> Option<Vec<String>> {
+        let square = _postion.parse().ok()?;
+        let mut string_positions: Vec<String> = vec!();
+        for mv in self.position.moves_from(square) {
+            // promotions give the same new position once for every piece
+            if !string_positions.contains(&mv.to().to_string()) {
+                string_positions.push(mv.to().to_string());
             }
         }
-        return if string_positions.len() > 0 {Some(string_positions)} else {None};
+        if string_positions.is_empty() {None} else {Some(string_positions)}
+    }
+}
+
+impl HistoryEntry {
+    /// The move that was played.
+    pub fn mv(&self) -> Move {
+        self.mv
+    }
+
+    /// The piece that moved, a pawn for promotions.
+    pub fn piece(&self) -> Piece {
+        self.undo.piece.expect("a piece moved")
+    }
+
+    /// The piece that was captured by the move, if any.
+    pub fn captured(&self) -> Option<Piece> {
+        self.undo.captured
     }
 }
 
 impl fmt::Debug for Game {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     /* build board representation string */
-    
+
     let mut repr: String = "".to_owned();
     let mut rank_idx = 9;
 
     repr.push_str("\n\n  A B C D E F G H\n");
 
-    for rank in &self.board {
+    for rank in &self.position.board() {
         rank_idx -= 1;
         repr.push_str(&rank_idx.to_string());
-        repr.push_str(" ");
+        repr.push(' ');
 
         for piece in rank {
-            if piece.is_none() {repr.push_str("* ");} else {repr.push_str(&piece.as_ref().unwrap().icon()); repr.push_str(" ");}
+            match piece {
+                Some(piece) => {repr.push_str(&piece.icon()); repr.push(' ');},
+                None => repr.push_str("* ")
+            }
         }
-        repr.push_str("\n");
+        repr.push('\n');
     }
     write!(f, "{}", repr)
     }
 }
 
-fn pos_from_string(position: &String) -> [usize; 2]{
-        let mut positions = position.chars();
-        let file = positions.next().unwrap();
-        let rank = positions.next().unwrap();
-        let rank_idx = (8 - rank.to_digit(10).unwrap()) as usize;
-        let file_idx = match file {
-            'a' => 0 as usize, 'A' => 0 as usize, 'b' => 1 as usize, 'B' => 1 as usize,
-            'c' => 2 as usize, 'C' => 2 as usize, 'd' => 3 as usize, 'D' => 3 as usize,
-            'e' => 4 as usize, 'E' => 4 as usize, 'f' => 5 as usize, 'F' => 5 as usize,
-            'g' => 6 as usize, 'G' => 6 as usize, 'h' => 7 as usize, 'H' => 7 as usize,
-            _ => 8 as usize //why can this happen?
-        };
-        return [file_idx, rank_idx];
+/// Return the square with the index in the lowest six bits of `bits`.
+fn square_of(bits: u16) -> Square {
+    let index = (bits & 63) as usize;
+    Square::new(File::ALL[index % 8], Rank::ALL[index / 8])
 }
 
-fn pos_to_string(coords: [usize; 2]) -> String {
-    let mut position = "".to_string();
-    let rank = 8 - coords[1];
-    position.push_str(
-        match coords[0] {
-            0 => "a", 1 => "b", 2 => "c", 3 => "d",
-            4 => "e", 5 => "f", 6 => "g", 7 => "h",
-            _ => "i"
-        }
-    );
-    position.push_str(&(rank).to_string());
-    return position;
+/// Read a square given to the string based API, e.g. "E4".
+fn parse_square(square: &str) -> Result<Square, MoveError> {
+    square.parse().map_err(|_| MoveError::InvalidSquare(square.to_string()))
 }
 
 // --------------------------
@@ -552,7 +651,37 @@ fn pos_to_string(coords: [usize; 2]) -> String {
 mod tests {
     use super::Game;
     use super::GameState;
-    use super::pos_from_string;
+    use super::Termination;
+    use super::MoveError;
+    use super::Color;
+    use super::Piece;
+    use super::Square;
+    use super::Move;
+    use super::Promotion;
+    use super::position::{board_index, Position};
+
+    /// The state of a game won by checkmate.
+    fn game_over(winner: Color) -> GameState {
+        GameState::GameOver {winner: Some(winner), reason: Termination::Checkmate}
+    }
+
+    /// Read a square such as "E4".
+    fn sq(square: &str) -> Square {
+        square.parse().unwrap()
+    }
+
+    /// Build a game with only the given pieces on the board and `active` to move.
+    fn game_with(active: Color, pieces: &[(&str, Piece)]) -> Game {
+        let mut game = Game::new();
+        let castling = game.position.castling;
+        game.position = Position::empty();
+        game.position.active = active;
+        game.position.castling = castling;
+        for (position, piece) in pieces {
+            game.position.set_piece(sq(position), Some(*piece));
+        }
+        game
+    }
 
     // check test framework
     #[test]
@@ -568,24 +697,24 @@ mod tests {
         println!("{:?}", game);
         assert_eq!(game.get_game_state(), GameState::InProgress);
     }
-    
+
     #[test]
     fn get_piece() {
         let game = Game::new();
         let position = &"B1".to_string();
-        let piece = game.get_piece(pos_from_string(position));
-        let icon = if piece.is_none() {"*".to_string()} else {piece.unwrap().icon()};
+        let piece = game.position.get_piece(sq(position));
+        let icon = if let Some(piece) = piece {piece.icon()} else {"*".to_string()};
         println!("\n\nPiece at {}: {}\n", position, icon);
     }
-    
+
     #[test]
     fn possib_moves() {
         let game = Game::new();
         let position = "B1".to_string().to_owned();
         let possible_moves = game.get_possible_moves(position);
-        if !possible_moves.is_none() {
+        if let Some(possible_moves) = possible_moves {
             println!();
-            for pos in possible_moves.unwrap() {
+            for pos in possible_moves {
                 println!("{}", pos);
             }
         }
@@ -594,7 +723,557 @@ mod tests {
     #[test]
     fn test_move() {
         let mut game = Game::new();
-        game.make_move("B8".to_string(), "C6".to_string());
+        game.make_move("B1".to_string(), "C3".to_string()).unwrap();
         println!("{:?}", game);
     }
+
+    #[test]
+    fn check_is_detected() {
+        let mut game = game_with(Color::Black, &[
+            ("A1", Piece::King(Color::White)),
+            ("C3", Piece::King(Color::Black)),
+            ("B5", Piece::Queen(Color::Black))
+        ]);
+        assert_eq!(game.make_move("B5".to_string(), "B1".to_string()), Ok(GameState::Check));
+    }
+
+    #[test]
+    fn checkmate_ends_game() {
+        let mut game = game_with(Color::Black, &[
+            ("A1", Piece::King(Color::White)),
+            ("C3", Piece::King(Color::Black)),
+            ("B5", Piece::Queen(Color::Black))
+        ]);
+        let expected = GameState::GameOver {
+            winner: Some(Color::Black),
+            reason: Termination::Checkmate
+        };
+        assert_eq!(game.make_move("B5".to_string(), "B2".to_string()), Ok(expected));
+        assert_eq!(game.get_game_state(), expected);
+        assert_eq!(game.make_move("C3".to_string(), "C4".to_string()), Err(MoveError::GameFinished));
+    }
+
+    #[test]
+    fn stalemate_ends_game() {
+        let mut game = game_with(Color::Black, &[
+            ("A1", Piece::King(Color::White)),
+            ("C2", Piece::King(Color::Black)),
+            ("B5", Piece::Queen(Color::Black))
+        ]);
+        let expected = GameState::GameOver {
+            winner: None,
+            reason: Termination::Stalemate
+        };
+        assert_eq!(game.make_move("B5".to_string(), "B3".to_string()), Ok(expected));
+    }
+
+    #[test]
+    fn blocked_check_is_not_mate() {
+        let mut game = game_with(Color::Black, &[
+            ("A1", Piece::King(Color::White)),
+            ("B4", Piece::Rook(Color::White)),
+            ("C3", Piece::King(Color::Black)),
+            ("H8", Piece::Queen(Color::Black))
+        ]);
+        assert_eq!(game.make_move("H8".to_string(), "H1".to_string()), Ok(GameState::Check));
+    }
+
+    #[test]
+    fn pawns_double_step_only_from_their_starting_rank() {
+        let game = game_with(Color::White, &[
+            ("H1", Piece::King(Color::White)),
+            ("H8", Piece::King(Color::Black)),
+            ("E2", Piece::Pawn(Color::White)),
+            ("D3", Piece::Pawn(Color::White)),
+            ("C7", Piece::Pawn(Color::Black)),
+            ("B6", Piece::Pawn(Color::Black))
+        ]);
+        let moves = |position: &str| {
+            let mut moves = game.get_possible_moves(position.to_string()).unwrap_or_default();
+            moves.sort();
+            moves
+        };
+        assert_eq!(moves("E2"), ["e3", "e4"]);
+        assert_eq!(moves("D3"), ["d4"]);
+        assert_eq!(moves("C7"), ["c5", "c6"]);
+        assert_eq!(moves("B6"), ["b5"]);
+    }
+
+    #[test]
+    fn black_pawns_capture_diagonally_forward() {
+        let game = game_with(Color::White, &[
+            ("H1", Piece::King(Color::White)),
+            ("H8", Piece::King(Color::Black)),
+            ("D7", Piece::Pawn(Color::Black)),
+            ("C6", Piece::Knight(Color::White)),
+            ("E6", Piece::Knight(Color::White)),
+            ("A5", Piece::Pawn(Color::Black)),
+            ("B4", Piece::Knight(Color::White))
+        ]);
+        let moves = |position: &str| {
+            let mut moves = game.get_possible_moves(position.to_string()).unwrap_or_default();
+            moves.sort();
+            moves
+        };
+        assert_eq!(moves("D7"), ["c6", "d5", "d6", "e6"]);
+        assert_eq!(moves("A5"), ["a4", "b4"]);
+    }
+
+    #[test]
+    fn pinned_piece_cannot_move() {
+        let game = game_with(Color::White, &[
+            ("E1", Piece::King(Color::White)),
+            ("E2", Piece::Bishop(Color::White)),
+            ("E8", Piece::Rook(Color::Black))
+        ]);
+        assert_eq!(game.get_possible_moves("E2".to_string()), None);
+    }
+
+    #[test]
+    fn king_cannot_move_into_check() {
+        let game = game_with(Color::White, &[
+            ("A1", Piece::King(Color::White)),
+            ("B8", Piece::Rook(Color::Black))
+        ]);
+        assert_eq!(game.get_possible_moves("A1".to_string()), Some(vec!["a2".to_string()]));
+    }
+
+    #[test]
+    fn illegal_move_is_rejected() {
+        let mut game = game_with(Color::Black, &[
+            ("E8", Piece::King(Color::Black)),
+            ("E7", Piece::Knight(Color::Black)),
+            ("E1", Piece::Rook(Color::White)),
+            ("A1", Piece::King(Color::White))
+        ]);
+        assert_eq!(game.make_move("E7".to_string(), "C6".to_string()), Err(MoveError::IllegalMove));
+        assert_eq!(game.position.get_piece(sq("E7")), Some(Piece::Knight(Color::Black)));
+    }
+
+    #[test]
+    fn only_moves_resolving_check_are_possible() {
+        let game = game_with(Color::White, &[
+            ("E1", Piece::King(Color::White)),
+            ("A2", Piece::Rook(Color::White)),
+            ("E8", Piece::Rook(Color::Black)),
+            ("H8", Piece::King(Color::Black))
+        ]);
+        assert_eq!(game.get_possible_moves("A2".to_string()), Some(vec!["e2".to_string()]));
+    }
+
+    #[test]
+    fn castling_moves_are_possible() {
+        let game = game_with(Color::White, &[
+            ("E1", Piece::King(Color::White)),
+            ("A1", Piece::Rook(Color::White)),
+            ("H1", Piece::Rook(Color::White)),
+            ("E8", Piece::King(Color::Black))
+        ]);
+        let moves = game.get_possible_moves("E1".to_string()).unwrap();
+        assert!(moves.contains(&"g1".to_string()));
+        assert!(moves.contains(&"c1".to_string()));
+    }
+
+    #[test]
+    fn cannot_castle_out_of_or_through_check() {
+        let through = game_with(Color::White, &[
+            ("E1", Piece::King(Color::White)),
+            ("A1", Piece::Rook(Color::White)),
+            ("H1", Piece::Rook(Color::White)),
+            ("F8", Piece::Rook(Color::Black)),
+            ("A8", Piece::King(Color::Black))
+        ]);
+        let moves = through.get_possible_moves("E1".to_string()).unwrap();
+        assert!(!moves.contains(&"g1".to_string()));
+        assert!(moves.contains(&"c1".to_string()));
+
+        let out_of = game_with(Color::White, &[
+            ("E1", Piece::King(Color::White)),
+            ("A1", Piece::Rook(Color::White)),
+            ("H1", Piece::Rook(Color::White)),
+            ("E7", Piece::Rook(Color::Black)),
+            ("A8", Piece::King(Color::Black))
+        ]);
+        let moves = out_of.get_possible_moves("E1".to_string()).unwrap();
+        assert!(!moves.contains(&"g1".to_string()));
+        assert!(!moves.contains(&"c1".to_string()));
+    }
+
+    #[test]
+    fn castling_moves_the_rook() {
+        let mut game = game_with(Color::Black, &[
+            ("E8", Piece::King(Color::Black)),
+            ("H8", Piece::Rook(Color::Black)),
+            ("E1", Piece::King(Color::White))
+        ]);
+        game.make_move("E8".to_string(), "G8".to_string()).unwrap();
+        assert_eq!(game.position.get_piece(sq("G8")), Some(Piece::King(Color::Black)));
+        assert_eq!(game.position.get_piece(sq("F8")), Some(Piece::Rook(Color::Black)));
+        assert_eq!(game.position.get_piece(sq("H8")), None);
+    }
+
+    #[test]
+    fn castling_rights_are_lost() {
+        let mut game = game_with(Color::Black, &[
+            ("E8", Piece::King(Color::Black)),
+            ("A8", Piece::Rook(Color::Black)),
+            ("H8", Piece::Rook(Color::Black)),
+            ("F3", Piece::Bishop(Color::Black)),
+            ("E1", Piece::King(Color::White)),
+            ("A1", Piece::Rook(Color::White)),
+            ("H1", Piece::Rook(Color::White)),
+            ("A2", Piece::Pawn(Color::White))
+        ]);
+        game.make_move("H8".to_string(), "H7".to_string()).unwrap();
+        game.make_move("A2".to_string(), "A3".to_string()).unwrap();
+        game.make_move("H7".to_string(), "H8".to_string()).unwrap();
+        game.make_move("A3".to_string(), "A4".to_string()).unwrap();
+        let moves = game.get_possible_moves("E8".to_string()).unwrap();
+        assert!(!moves.contains(&"g8".to_string()));
+        assert!(moves.contains(&"c8".to_string()));
+
+        // capturing a rook removes the right to castle with it
+        game.make_move("F3".to_string(), "H1".to_string()).unwrap();
+        let moves = game.get_possible_moves("E1".to_string()).unwrap();
+        assert!(!moves.contains(&"g1".to_string()));
+        assert!(moves.contains(&"c1".to_string()));
+    }
+
+    #[test]
+    fn en_passant_is_possible_after_double_step() {
+        let mut game = game_with(Color::Black, &[
+            ("E5", Piece::Pawn(Color::White)),
+            ("H2", Piece::Pawn(Color::White)),
+            ("D7", Piece::Pawn(Color::Black)),
+            ("A7", Piece::Pawn(Color::Black)),
+            ("E1", Piece::King(Color::White)),
+            ("E8", Piece::King(Color::Black))
+        ]);
+        game.make_move("D7".to_string(), "D5".to_string()).unwrap();
+        assert!(game.get_possible_moves("E5".to_string()).unwrap().contains(&"d6".to_string()));
+
+        // the right to capture en passant only lasts for one move
+        game.make_move("H2".to_string(), "H3".to_string()).unwrap();
+        game.make_move("A7".to_string(), "A6".to_string()).unwrap();
+        assert!(!game.get_possible_moves("E5".to_string()).unwrap().contains(&"d6".to_string()));
+    }
+
+    #[test]
+    fn en_passant_removes_captured_pawn() {
+        let mut game = game_with(Color::Black, &[
+            ("E4", Piece::Pawn(Color::White)),
+            ("D4", Piece::Pawn(Color::Black)),
+            ("A1", Piece::King(Color::White)),
+            ("H8", Piece::King(Color::Black))
+        ]);
+        game.position.en_passant = Some(sq("E3"));
+        game.make_move("D4".to_string(), "E3".to_string()).unwrap();
+        assert_eq!(game.position.get_piece(sq("E3")), Some(Piece::Pawn(Color::Black)));
+        assert_eq!(game.position.get_piece(sq("E4")), None);
+    }
+
+    #[test]
+    fn pawn_is_promoted_to_queen_by_default() {
+        let mut game = game_with(Color::Black, &[
+            ("B2", Piece::Pawn(Color::Black)),
+            ("H1", Piece::King(Color::White)),
+            ("H8", Piece::King(Color::Black))
+        ]);
+        assert_eq!(game.make_move("B2".to_string(), "B1".to_string()), Ok(GameState::Check));
+        assert_eq!(game.position.get_piece(sq("B1")), Some(Piece::Queen(Color::Black)));
+    }
+
+    #[test]
+    fn set_promotion_chooses_piece() {
+        let mut game = game_with(Color::Black, &[
+            ("B2", Piece::Pawn(Color::Black)),
+            ("D5", Piece::Pawn(Color::White)),
+            ("H1", Piece::King(Color::White)),
+            ("H8", Piece::King(Color::Black))
+        ]);
+        game.set_promotion("knight".to_string());
+        assert_eq!(game.make_move("B2".to_string(), "B1".to_string()), Ok(GameState::InProgress));
+        assert_eq!(game.position.get_piece(sq("B1")), Some(Piece::Knight(Color::Black)));
+    }
+
+    #[test]
+    fn promotion_piece_can_be_given_with_move() {
+        let mut game = game_with(Color::Black, &[
+            ("B2", Piece::Pawn(Color::Black)),
+            ("H1", Piece::King(Color::White)),
+            ("H8", Piece::King(Color::Black))
+        ]);
+        assert_eq!(game.make_move_with_promotion("B2".to_string(), "B1".to_string(), "x".to_string()), Err(MoveError::PromotionRequired));
+        game.make_move_with_promotion("B2".to_string(), "B1".to_string(), "r".to_string()).unwrap();
+        assert_eq!(game.position.get_piece(sq("B1")), Some(Piece::Rook(Color::Black)));
+        assert_eq!(game.promotion, Promotion::Queen);
+    }
+
+    #[test]
+    fn turns_alternate() {
+        let mut game = Game::new();
+        assert_eq!(game.active_color(), Color::White);
+        assert_eq!(game.make_move("B8".to_string(), "C6".to_string()), Err(MoveError::WrongTurn));
+
+        assert_eq!(game.make_move("B1".to_string(), "C3".to_string()), Ok(GameState::InProgress));
+        assert_eq!(game.active_color(), Color::Black);
+        assert_eq!(game.make_move("C3".to_string(), "B5".to_string()), Err(MoveError::WrongTurn));
+
+        assert_eq!(game.make_move("B8".to_string(), "C6".to_string()), Ok(GameState::InProgress));
+        assert_eq!(game.active_color(), Color::White);
+    }
+
+    #[test]
+    fn side_in_check_can_move() {
+        let mut game = game_with(Color::Black, &[
+            ("A1", Piece::King(Color::White)),
+            ("C3", Piece::King(Color::Black)),
+            ("B5", Piece::Queen(Color::Black)),
+            ("H5", Piece::Pawn(Color::Black))
+        ]);
+        assert_eq!(game.make_move("B5".to_string(), "B1".to_string()), Ok(GameState::Check));
+        assert_eq!(game.make_move("A1".to_string(), "B1".to_string()), Ok(GameState::InProgress));
+    }
+
+    #[test]
+    fn invalid_input_is_reported() {
+        let mut game = Game::new();
+        assert_eq!(game.make_move("I2".to_string(), "E4".to_string()), Err(MoveError::InvalidSquare("I2".to_string())));
+        assert_eq!(game.make_move("E2".to_string(), "E9".to_string()), Err(MoveError::InvalidSquare("E9".to_string())));
+        assert_eq!(game.make_move("E2".to_string(), "".to_string()), Err(MoveError::InvalidSquare("".to_string())));
+        assert_eq!(game.make_move("E4".to_string(), "E5".to_string()), Err(MoveError::NoPiece));
+        assert_eq!(game.make_move("E2".to_string(), "E5".to_string()), Err(MoveError::IllegalMove));
+        assert_eq!(game.get_possible_moves("E22".to_string()), None);
+    }
+
+    #[test]
+    fn promotion_requires_valid_piece() {
+        let mut game = game_with(Color::White, &[
+            ("B7", Piece::Pawn(Color::White)),
+            ("H2", Piece::Pawn(Color::White)),
+            ("H1", Piece::King(Color::White)),
+            ("H8", Piece::King(Color::Black))
+        ]);
+        assert_eq!(game.make_move_with_promotion("B7".to_string(), "B8".to_string(), "".to_string()), Err(MoveError::PromotionRequired));
+        // the piece is ignored for moves that are not promotions
+        assert_eq!(game.make_move_with_promotion("H2".to_string(), "H3".to_string(), "".to_string()), Ok(GameState::InProgress));
+    }
+
+    #[test]
+    fn moves_are_read_and_written() {
+        assert_eq!("e2e4".parse::<Move>(), Ok(Move::new(sq("E2"), sq("E4"))));
+        assert_eq!("E7E8Q".parse::<Move>(), Ok(Move::with_promotion(sq("E7"), sq("E8"), Promotion::Queen)));
+        assert_eq!(Move::with_promotion(sq("B2"), sq("A1"), Promotion::Knight).to_string(), "b2a1n");
+        for s in ["", "e2", "e2e9", "e7e8k", "e7e8qq", "e2-e4"] {
+            assert!(s.parse::<Move>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn typed_moves_can_be_played() {
+        let mut game = Game::new();
+        assert_eq!(game.moves_from(sq("G1")).len(), 2);
+        assert_eq!(game.play("g1f3".parse().unwrap()), Ok(GameState::InProgress));
+        assert_eq!(game.piece_at(sq("F3")), Some(Piece::Knight(Color::White)));
+        assert_eq!(game.play(Move::new(sq("E7"), sq("E4"))), Err(MoveError::IllegalMove));
+    }
+
+    #[test]
+    fn typed_promotion_needs_piece() {
+        let mut game = game_with(Color::White, &[
+            ("B7", Piece::Pawn(Color::White)),
+            ("A2", Piece::Pawn(Color::White)),
+            ("H1", Piece::King(Color::White)),
+            ("H8", Piece::King(Color::Black))
+        ]);
+        assert_eq!(game.moves_from(sq("B7")).len(), 4);
+        assert_eq!(game.play(Move::new(sq("B7"), sq("B8"))), Err(MoveError::PromotionRequired));
+        assert_eq!(game.play(Move::with_promotion(sq("B7"), sq("B8"), Promotion::Bishop)), Ok(GameState::InProgress));
+        assert_eq!(game.piece_at(sq("B8")), Some(Piece::Bishop(Color::White)));
+    }
+
+    #[test]
+    fn kings_and_queens_start_on_their_files() {
+        let game = Game::new();
+        assert_eq!(game.piece_at(sq("D1")), Some(Piece::Queen(Color::White)));
+        assert_eq!(game.piece_at(sq("E1")), Some(Piece::King(Color::White)));
+        assert_eq!(game.piece_at(sq("D8")), Some(Piece::Queen(Color::Black)));
+        assert_eq!(game.piece_at(sq("E8")), Some(Piece::King(Color::Black)));
+        assert_eq!(game.get_possible_moves("E1".to_string()), None);
+        assert_eq!(game.to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    }
+
+    #[test]
+    fn board_index_matches_squares() {
+        assert_eq!(board_index(sq("A8")), (0, 0));
+        assert_eq!(board_index(sq("H8")), (0, 7));
+        assert_eq!(board_index(sq("A1")), (7, 0));
+        assert_eq!(board_index(sq("H1")), (7, 7));
+        assert_eq!(board_index(sq("E2")), (6, 4));
+
+        let game = Game::new();
+        assert_eq!(game.position.board()[7][0], Some(Piece::Rook(Color::White)));
+        assert_eq!(game.position.board()[0][4], Some(Piece::King(Color::Black)));
+        assert_eq!(game.position.board()[6][4], Some(Piece::Pawn(Color::White)));
+    }
+
+    #[test]
+    fn castling_is_possible_from_start() {
+        let mut game = Game::new();
+        for (from, to) in [("E2", "E4"), ("E7", "E5"), ("G1", "F3"), ("B8", "C6"), ("F1", "C4"), ("G8", "F6")] {
+            game.make_move(from.to_string(), to.to_string()).unwrap();
+        }
+        game.make_move("E1".to_string(), "G1".to_string()).unwrap();
+        assert_eq!(game.piece_at(sq("F1")), Some(Piece::Rook(Color::White)));
+    }
+
+    #[test]
+    fn moves_can_be_undone_and_redone() {
+        // includes en passant, promotion with capture and castling
+        let moves = [
+            "e2e4", "d7d5", "e4d5", "c7c5", "d5c6", "g8f6",
+            "c6b7", "e7e6", "b7a8q", "f8e7", "g1f3", "e8g8"
+        ];
+        let mut game = Game::new();
+        let mut fens = vec!();
+        for mv in moves {
+            fens.push(game.to_fen());
+            game.play(mv.parse().unwrap()).unwrap();
+        }
+        let final_fen = game.to_fen();
+
+        assert_eq!(game.history().len(), moves.len());
+        assert_eq!(game.history()[4].captured(), Some(Piece::Pawn(Color::Black)));
+        assert_eq!(game.history()[8].piece(), Piece::Pawn(Color::White));
+        assert_eq!(game.history()[8].captured(), Some(Piece::Rook(Color::Black)));
+        assert_eq!(game.history()[8].mv().to_string(), "b7a8q");
+
+        for (mv, fen) in moves.iter().zip(fens.iter()).rev() {
+            assert_eq!(game.undo_move().map(|undone| undone.to_string()), Some(mv.to_string()));
+            assert_eq!(&game.to_fen(), fen);
+        }
+        assert_eq!(game.undo_move(), None);
+        assert!(game.history().is_empty());
+
+        while game.redo_move().is_some() {}
+        assert_eq!(game.to_fen(), final_fen);
+        assert_eq!(game.history().len(), moves.len());
+    }
+
+    #[test]
+    fn new_move_clears_redo() {
+        let mut game = Game::new();
+        game.make_move("E2".to_string(), "E4".to_string()).unwrap();
+        game.undo_move().unwrap();
+        game.make_move("D2".to_string(), "D4".to_string()).unwrap();
+        assert_eq!(game.redo_move(), None);
+    }
+
+    #[test]
+    fn undo_restores_game_state() {
+        let mut game = Game::new();
+        for (from, to) in [("F2", "F3"), ("E7", "E5"), ("G2", "G4"), ("D8", "H4")] {
+            game.make_move(from.to_string(), to.to_string()).unwrap();
+        }
+        assert!(matches!(game.get_game_state(), GameState::GameOver { .. }));
+        game.undo_move().unwrap();
+        assert_eq!(game.get_game_state(), GameState::InProgress);
+        assert_eq!(game.active_color(), Color::Black);
+        assert_eq!(game.redo_move(), Some(game_over(Color::Black)));
+    }
+
+    #[test]
+    fn games_ended_by_a_player_cannot_be_undone() {
+        let mut game = Game::new();
+        game.resign(Color::White).unwrap();
+        assert_eq!(game.undo_move(), None);
+
+        let mut game = Game::new();
+        game.make_move("E2".to_string(), "E4".to_string()).unwrap();
+        game.make_move("E7".to_string(), "E5".to_string()).unwrap();
+        game.undo_move().unwrap();
+        game.offer_draw(Color::White).unwrap();
+        let agreed = game.accept_draw(Color::Black).unwrap();
+        assert_eq!(game.undo_move(), None);
+        assert_eq!(game.redo_move(), None);
+        assert_eq!(game.get_game_state(), agreed);
+        assert_eq!(game.history().len(), 1);
+    }
+
+    #[test]
+    fn undo_restores_draw_offers() {
+        let mut game = Game::new();
+        game.make_move("E2".to_string(), "E4".to_string()).unwrap();
+        game.offer_draw(Color::White).unwrap();
+        // black declines by moving
+        game.make_move("E7".to_string(), "E5".to_string()).unwrap();
+        assert_eq!(game.draw_offer(), None);
+        game.undo_move().unwrap();
+        assert_eq!(game.draw_offer(), Some(Color::White));
+        assert_eq!(game.accept_draw(Color::Black).map(|_| game.termination()), Ok(Some(Termination::Agreement)));
+    }
+
+    #[test]
+    fn insufficient_material_is_a_draw() {
+        let draws = [
+            "8/8/4k3/8/8/3K4/8/8 w - - 0 1",
+            "8/8/4k3/8/8/3KB3/8/8 w - - 0 1",
+            "8/8/4kn2/8/8/3K4/8/8 w - - 0 1",
+            "8/1b6/4k3/8/8/3K1B2/8/8 w - - 0 1"
+        ];
+        for fen in draws {
+            let expected = GameState::GameOver {winner: None, reason: Termination::InsufficientMaterial};
+            assert_eq!(Game::from_fen(fen).unwrap().get_game_state(), expected, "{}", fen);
+        }
+
+        let not_draws = [
+            "8/8/4k3/8/8/3K4/4P3/8 w - - 0 1",
+            "8/8/4k3/8/8/3KN3/8/4N3 w - - 0 1",
+            "8/2b5/4k3/8/8/3K1B2/8/8 w - - 0 1"
+        ];
+        for fen in not_draws {
+            assert_eq!(Game::from_fen(fen).unwrap().get_game_state(), GameState::InProgress, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn fifty_moves_allow_a_claim_and_seventy_five_end_the_game() {
+        let mut game = Game::from_fen("8/8/4k3/8/8/3K4/R7/8 w - - 99 80").unwrap();
+        assert_eq!(game.claimable_draw(), None);
+        assert_eq!(game.claim_draw(), None);
+        assert_eq!(game.play("a2a1".parse().unwrap()), Ok(GameState::InProgress));
+        assert_eq!(game.halfmove_clock(), 100);
+        assert_eq!(game.claimable_draw(), Some(Termination::FiftyMoveRule));
+        let expected = GameState::GameOver {winner: None, reason: Termination::FiftyMoveRule};
+        assert_eq!(game.claim_draw(), Some(expected));
+        assert_eq!(game.play("e6f6".parse().unwrap()), Err(MoveError::GameFinished));
+
+        let mut game = Game::from_fen("8/8/4k3/8/8/3K4/R7/8 b - - 149 100").unwrap();
+        let expected = GameState::GameOver {winner: None, reason: Termination::SeventyFiveMoveRule};
+        assert_eq!(game.play("e6f6".parse().unwrap()), Ok(expected));
+    }
+
+    #[test]
+    fn repetitions_allow_a_claim_and_end_the_game() {
+        let mut game = Game::new();
+        let play_knights = |game: &mut Game| {
+            for (from, to) in [("G1", "F3"), ("G8", "F6"), ("F3", "G1"), ("F6", "G8")] {
+                game.make_move(from.to_string(), to.to_string()).unwrap();
+            }
+        };
+
+        play_knights(&mut game);
+        assert_eq!(game.claimable_draw(), None);
+        play_knights(&mut game);
+        assert_eq!(game.claimable_draw(), Some(Termination::ThreefoldRepetition));
+        play_knights(&mut game);
+        play_knights(&mut game);
+        let expected = GameState::GameOver {winner: None, reason: Termination::FivefoldRepetition};
+        assert_eq!(game.get_game_state(), expected);
+        assert_eq!(game.claimable_draw(), None);
+
+        game.undo_move().unwrap();
+        assert_eq!(game.get_game_state(), GameState::InProgress);
+        assert_eq!(game.redo_move(), Some(expected));
+    }
 }
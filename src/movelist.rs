@@ -0,0 +1,219 @@
+use std::ops::{Deref, DerefMut};
+
+use crate::{Game, Move, Square};
+
+/// The most moves a list can hold, more than any position has: the
+/// record is 218 legal moves.
+const CAPACITY: usize = 256;
+
+/// A list of moves that lives on the stack, so that generating moves does
+/// not allocate. It dereferences to a slice of its moves, e.g. to sort them.
+#[derive(Copy, Clone)]
+pub struct MoveList {
+    moves: [Move; CAPACITY],
+    len: usize
+}
+
+/// An iterator over the moves of a `MoveList` it owns.
+#[derive(Clone)]
+pub struct MoveListIter {
+    list: MoveList,
+    next: usize
+}
+
+impl MoveList {
+    /// An empty list.
+    pub fn new() -> MoveList {
+        let square = Square::from_index(0).expect("a1 is a square");
+        MoveList {moves: [Move::new(square, square); CAPACITY], len: 0}
+    }
+
+    /// Add a move at the end. Panics if the list is full.
+    pub fn push(&mut self, mv: Move) {
+        assert!(self.len < CAPACITY, "more than {} moves", CAPACITY);
+        self.moves[self.len] = mv;
+        self.len += 1;
+    }
+
+    /// Remove all moves.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Keep only the moves for which `keep` returns true, in their order.
+    pub fn retain(&mut self, mut keep: impl FnMut(&Move) -> bool) {
+        let mut len = 0;
+        for i in 0..self.len {
+            if keep(&self.moves[i]) {
+                self.moves[len] = self.moves[i];
+                len += 1;
+            }
+        }
+        self.len = len;
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        MoveList::new()
+    }
+}
+
+impl Deref for MoveList {
+    type Target = [Move];
+
+    fn deref(&self) -> &[Move] {
+        &self.moves[..self.len]
+    }
+}
+
+impl DerefMut for MoveList {
+    fn deref_mut(&mut self) -> &mut [Move] {
+        &mut self.moves[..self.len]
+    }
+}
+
+impl std::fmt::Debug for MoveList {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl PartialEq for MoveList {
+    fn eq(&self, other: &MoveList) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for MoveList {}
+
+impl Extend<Move> for MoveList {
+    fn extend<I: IntoIterator<Item = Move>>(&mut self, moves: I) {
+        for mv in moves {
+            self.push(mv);
+        }
+    }
+}
+
+impl std::iter::FromIterator<Move> for MoveList {
+    fn from_iter<I: IntoIterator<Item = Move>>(moves: I) -> MoveList {
+        let mut list = MoveList::new();
+        list.extend(moves);
+        list
+    }
+}
+
+impl IntoIterator for MoveList {
+    type Item = Move;
+    type IntoIter = MoveListIter;
+
+    fn into_iter(self) -> MoveListIter {
+        MoveListIter {list: self, next: 0}
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a Move;
+    type IntoIter = std::slice::Iter<'a, Move>;
+
+    fn into_iter(self) -> std::slice::Iter<'a, Move> {
+        self.iter()
+    }
+}
+
+impl Iterator for MoveListIter {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        let mv = self.list.get(self.next).copied();
+        self.next += 1;
+        mv
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.list.len().saturating_sub(self.next);
+        (left, Some(left))
+    }
+}
+
+impl ExactSizeIterator for MoveListIter {}
+
+impl Game {
+    /// Return all legal moves of the side to move, none if the game is over.
+    /// A pawn reaching the last rank gives one move for every promotion.
+    pub fn legal_moves(&self) -> MoveList {
+        if self.outcome().is_some() {
+            return MoveList::new();
+        }
+        self.position.legal_moves()
+    }
+
+    /// Return the legal moves that capture a piece, also en passant.
+    pub fn captures(&self) -> MoveList {
+        let mut moves = self.legal_moves();
+        moves.retain(|mv| self.position.is_capture(mv));
+        moves
+    }
+
+    /// Return the legal moves that neither capture nor promote.
+    pub fn quiet_moves(&self) -> MoveList {
+        let mut moves = self.legal_moves();
+        moves.retain(|mv| !self.position.is_capture(mv) && mv.promotion().is_none());
+        moves
+    }
+
+    /// Return the legal moves that give check.
+    pub fn checks(&self) -> MoveList {
+        let mut moves = self.legal_moves();
+        moves.retain(|mv| self.position.gives_check(mv));
+        moves
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MoveList;
+    use crate::{Game, Move};
+
+    fn names(moves: MoveList) -> Vec<String> {
+        moves.into_iter().map(|mv| mv.to_string()).collect()
+    }
+
+    #[test]
+    fn moves_take_two_bytes() {
+        assert_eq!(std::mem::size_of::<Move>(), 2);
+        let mv: Move = "b7a8n".parse().unwrap();
+        assert_eq!(mv.to_string(), "b7a8n");
+        assert_eq!((mv.from().to_string(), mv.to().to_string()), ("b7".to_string(), "a8".to_string()));
+    }
+
+    #[test]
+    fn lists_hold_the_legal_moves() {
+        let game = Game::new();
+        let moves = game.legal_moves();
+        assert_eq!(moves.len(), 20);
+        assert_eq!(moves.into_iter().len(), 20);
+        assert!(moves.contains(&"g1f3".parse().unwrap()));
+        assert_eq!(moves.iter().filter(|mv| mv.from().to_string() == "e2").count(), 2);
+
+        let collected: MoveList = moves.iter().copied().filter(|mv| mv.to().to_string() == "e4").collect();
+        assert_eq!(names(collected), ["e2e4"]);
+    }
+
+    #[test]
+    fn moves_can_be_filtered() {
+        let game = Game::from_fen("8/4k3/8/3pP3/8/8/8/R3K3 w Q d6 0 1").unwrap();
+        assert_eq!(names(game.captures()), ["e5d6"]);
+        assert_eq!(names(game.checks()), ["a1a7", "e5d6"]);
+        let quiet = game.quiet_moves();
+        assert!(quiet.contains(&"e1c1".parse().unwrap()));
+        assert!(!quiet.contains(&"e5d6".parse().unwrap()));
+        assert_eq!(quiet.len() + game.captures().len(), game.legal_moves().len());
+    }
+
+    #[test]
+    fn finished_games_have_no_moves() {
+        let game = Game::from_fen("7k/6Q1/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert!(game.legal_moves().is_empty());
+    }
+}
@@ -0,0 +1,212 @@
+use std::fmt;
+
+use crate::{Color, Game, GameState, MoveError, Piece, Square};
+
+/// The result of a finished game.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    WhiteWins,
+    BlackWins,
+    Draw
+}
+
+/// Why a game has ended.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    /// A player gave up with `resign`.
+    Resignation,
+    /// A player ran out of time, see `lose_on_time`.
+    Timeout,
+    /// Both players agreed to a draw with `offer_draw` and `accept_draw`.
+    Agreement,
+    /// Neither side has enough pieces left to checkmate.
+    InsufficientMaterial,
+    /// Claimed by a player after 50 moves by each side without a capture or pawn move.
+    FiftyMoveRule,
+    /// Claimed by a player when the same position occurred for the third time.
+    ThreefoldRepetition,
+    /// Automatic after 75 moves by each side without a capture or pawn move.
+    SeventyFiveMoveRule,
+    /// Automatic when the same position occurred for the fifth time.
+    FivefoldRepetition
+}
+
+impl Outcome {
+    /// The outcome of a game won by `winner`, a draw if there is no winner.
+    pub fn from_winner(winner: Option<Color>) -> Outcome {
+        match winner {
+            Some(Color::White) => Outcome::WhiteWins,
+            Some(Color::Black) => Outcome::BlackWins,
+            None => Outcome::Draw
+        }
+    }
+
+    /// Return the colour that won, None for a draw.
+    pub fn winner(self) -> Option<Color> {
+        match self {
+            Outcome::WhiteWins => Some(Color::White),
+            Outcome::BlackWins => Some(Color::Black),
+            Outcome::Draw => None
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    /// Write the outcome as in PGN: "1-0", "0-1" or "1/2-1/2".
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let result = match self {
+            Outcome::WhiteWins => "1-0",
+            Outcome::BlackWins => "0-1",
+            Outcome::Draw => "1/2-1/2"
+        };
+        write!(f, "{}", result)
+    }
+}
+
+impl Game {
+    /// Return the outcome of the game, None while it is not over.
+    pub fn outcome(&self) -> Option<Outcome> {
+        match self.state {
+            GameState::GameOver {winner, ..} => Some(Outcome::from_winner(winner)),
+            _ => None
+        }
+    }
+
+    /// Return why the game has ended, None while it is not over.
+    pub fn termination(&self) -> Option<Termination> {
+        match self.state {
+            GameState::GameOver {reason, ..} => Some(reason),
+            _ => None
+        }
+    }
+
+    /// End the game with `color` giving up, whoever's turn it is,
+    /// and return the resulting state.
+    pub fn resign(&mut self, color: Color) -> Result<GameState, MoveError> {
+        self.end(Some(color.opposite()), Termination::Resignation)
+    }
+
+    /// End the game with `color` having run out of time and return the
+    /// resulting state. The game is drawn instead of lost if the other
+    /// player has nothing but the king left to checkmate with.
+    pub fn lose_on_time(&mut self, color: Color) -> Result<GameState, MoveError> {
+        let opponent = color.opposite();
+        let only_king = Square::all().all(|square| match self.position.get_piece(square) {
+            Some(Piece::King(_)) | None => true,
+            Some(piece) => piece.color() != opponent
+        });
+        self.end(if only_king {None} else {Some(opponent)}, Termination::Timeout)
+    }
+
+    /// Offer a draw on behalf of `color`. The offer stands until the
+    /// other player accepts it with `accept_draw` or declines it by moving.
+    pub fn offer_draw(&mut self, color: Color) -> Result<(), MoveError> {
+        if matches!(self.state, GameState::GameOver { .. }) {
+            return Err(MoveError::GameFinished);
+        }
+        self.draw_offer = Some(color);
+        Ok(())
+    }
+
+    /// Return the player whose draw offer is still standing, if any.
+    pub fn draw_offer(&self) -> Option<Color> {
+        self.draw_offer
+    }
+
+    /// Accept on behalf of `color` the draw offered by the other player
+    /// and return the resulting state.
+    pub fn accept_draw(&mut self, color: Color) -> Result<GameState, MoveError> {
+        if self.draw_offer != Some(color.opposite()) {
+            return Err(MoveError::NoDrawOffer);
+        }
+        self.end(None, Termination::Agreement)
+    }
+
+    /// Return true if a player ended the game rather than a move, by
+    /// resigning, running out of time, agreeing to or claiming a draw.
+    pub(crate) fn ended_by_player(&self) -> bool {
+        matches!(self.state, GameState::GameOver {reason: Termination::Resignation | Termination::Timeout
+            | Termination::Agreement | Termination::FiftyMoveRule | Termination::ThreefoldRepetition, ..})
+    }
+
+    /// End the game unless it is over already.
+    pub(crate) fn end(&mut self, winner: Option<Color>, reason: Termination) -> Result<GameState, MoveError> {
+        if matches!(self.state, GameState::GameOver { .. }) {
+            return Err(MoveError::GameFinished);
+        }
+        self.draw_offer = None;
+        self.state = GameState::GameOver {winner, reason};
+        Ok(self.state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Outcome, Termination};
+    use crate::{Color, Game, GameState, MoveError};
+
+    #[test]
+    fn checkmate_has_an_outcome() {
+        let mut game = Game::new();
+        assert_eq!((game.outcome(), game.termination()), (None, None));
+        for (from, to) in [("F2", "F3"), ("E7", "E5"), ("G2", "G4"), ("D8", "H4")] {
+            game.make_move(from.to_string(), to.to_string()).unwrap();
+        }
+        assert_eq!(game.outcome(), Some(Outcome::BlackWins));
+        assert_eq!(game.termination(), Some(Termination::Checkmate));
+        assert_eq!(game.outcome().unwrap().to_string(), "0-1");
+    }
+
+    #[test]
+    fn players_can_resign() {
+        let mut game = Game::new();
+        let expected = GameState::GameOver {winner: Some(Color::White), reason: Termination::Resignation};
+        assert_eq!(game.resign(Color::Black), Ok(expected));
+        assert_eq!(game.outcome(), Some(Outcome::WhiteWins));
+        assert_eq!(game.resign(Color::White), Err(MoveError::GameFinished));
+        assert_eq!(game.play("e2e4".parse().unwrap()), Err(MoveError::GameFinished));
+    }
+
+    #[test]
+    fn running_out_of_time_loses_unless_the_opponent_cannot_win() {
+        let mut game = Game::new();
+        assert_eq!(game.lose_on_time(Color::White).unwrap(), GameState::GameOver {
+            winner: Some(Color::Black),
+            reason: Termination::Timeout
+        });
+
+        let mut lone_king = Game::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        lone_king.lose_on_time(Color::White).unwrap();
+        assert_eq!(lone_king.outcome(), Some(Outcome::Draw));
+        assert_eq!(lone_king.termination(), Some(Termination::Timeout));
+    }
+
+    #[test]
+    fn draws_can_be_offered_and_accepted() {
+        let mut game = Game::new();
+        assert_eq!(game.accept_draw(Color::Black), Err(MoveError::NoDrawOffer));
+
+        game.offer_draw(Color::White).unwrap();
+        assert_eq!(game.accept_draw(Color::White), Err(MoveError::NoDrawOffer));
+        game.play("e2e4".parse().unwrap()).unwrap();
+        assert_eq!(game.draw_offer(), Some(Color::White));
+        assert_eq!(game.accept_draw(Color::Black), Ok(GameState::GameOver {
+            winner: None,
+            reason: Termination::Agreement
+        }));
+        assert_eq!(game.outcome(), Some(Outcome::Draw));
+        assert_eq!(game.draw_offer(), None);
+    }
+
+    #[test]
+    fn moving_declines_a_draw() {
+        let mut game = Game::new();
+        game.offer_draw(Color::White).unwrap();
+        game.play("e2e4".parse().unwrap()).unwrap();
+        game.play("e7e5".parse().unwrap()).unwrap();
+        assert_eq!(game.draw_offer(), None);
+        assert_eq!(game.accept_draw(Color::Black), Err(MoveError::NoDrawOffer));
+    }
+}
@@ -0,0 +1,87 @@
+use crate::{Game, Move, Promotion};
+use crate::position::Position;
+
+impl Game {
+    /// Count the move sequences of the given number of half moves that can
+    /// be played from the current position, a check of the move generator
+    /// against well-known counts. Depth 0 counts the position itself.
+    pub fn perft(&self, depth: u32) -> u64 {
+        perft(&self.position, depth)
+    }
+
+    /// Like `perft`, but with the count split up by the first move,
+    /// which helps to find the move a generator bug hides behind.
+    pub fn perft_divide(&self, depth: u32) -> Vec<(Move, u64)> {
+        if depth == 0 {
+            return vec!();
+        }
+        self.position.legal_moves().into_iter()
+            .map(|mv| (mv, perft(&after(&self.position, &mv), depth - 1)))
+            .collect()
+    }
+}
+
+pub(crate) fn perft(position: &Position, depth: u32) -> u64 {
+    match depth {
+        0 => 1,
+        // the moves at the last level only need to be counted, not played
+        1 => position.legal_moves().len() as u64,
+        _ => position.legal_moves().iter()
+            .map(|mv| perft(&after(position, mv), depth - 1))
+            .sum()
+    }
+}
+
+/// Return the position after the legal move `mv`.
+fn after(position: &Position, mv: &Move) -> Position {
+    let mut after = *position;
+    after.apply_move(mv.from(), mv.to(), mv.promotion().unwrap_or(Promotion::Queen));
+    after
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Game;
+
+    /// Reference positions with their node counts at depth 1, 2, ...,
+    /// see https://www.chessprogramming.org/Perft_Results.
+    const POSITIONS: [(&str, &[u64]); 6] = [
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", &[20, 400, 8902, 197281]),
+        ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", &[48, 2039, 97862]),
+        ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", &[14, 191, 2812, 43238]),
+        ("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", &[6, 264, 9467]),
+        ("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", &[44, 1486, 62379]),
+        ("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10", &[46, 2079, 89890])
+    ];
+
+    #[test]
+    fn perft_matches_reference_counts() {
+        for (fen, counts) in POSITIONS {
+            let game = Game::from_fen(fen).unwrap();
+            assert_eq!(game.perft(0), 1);
+            for (depth, &count) in counts.iter().enumerate() {
+                assert_eq!(game.perft(depth as u32 + 1), count, "depth {} of {}", depth + 1, fen);
+            }
+        }
+    }
+
+    #[test]
+    fn perft_divide_adds_up() {
+        let game = Game::from_fen(POSITIONS[1].0).unwrap();
+        let divided = game.perft_divide(2);
+        assert_eq!(divided.len(), 48);
+        assert_eq!(divided.iter().map(|(_, count)| count).sum::<u64>(), 2039);
+        let castling = divided.iter().find(|(mv, _)| mv.to_string() == "e1g1").unwrap();
+        assert_eq!(castling.1, 43);
+    }
+
+    #[test]
+    fn black_pawns_capture_diagonally() {
+        // only captures are possible for the black pawns here
+        let game = Game::from_fen("4k3/8/8/8/8/1p1p4/PPPP4/4K3 b - - 0 1").unwrap();
+        let pawn_moves = game.perft_divide(1).into_iter()
+            .filter(|(mv, _)| mv.from().to_string() == "b3" || mv.from().to_string() == "d3")
+            .count();
+        assert_eq!(pawn_moves, 3);
+    }
+}
@@ -0,0 +1,680 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Color, FenError, Game, SanError, Termination};
+use crate::position::Position;
+
+/// The tags every PGN game has, in the order they are written.
+const SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+/// Movetext lines are kept shorter than this, as asked by the PGN standard.
+const MAX_LINE_LENGTH: usize = 80;
+
+/// A game in Portable Game Notation: the moves in a `Game` together with
+/// the tags describing where, when and by whom it was played.
+#[derive(Clone, Debug)]
+pub struct PgnGame {
+    /// Tag names and values in the order they were read or set.
+    tags: Vec<(String, String)>,
+    game: Game
+}
+
+/// Why a PGN file could not be read, with the line and column,
+/// both starting at 1, where the problem was found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PgnError {
+    line: usize,
+    column: usize,
+    kind: PgnErrorKind
+}
+
+/// What went wrong while reading a PGN file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PgnErrorKind {
+    /// A character that cannot appear in PGN outside of comments and strings.
+    UnexpectedCharacter(char),
+    /// A string or a `{` comment is not closed before the end of the file.
+    UnterminatedString,
+    /// A tag pair is not written as `[Name "value"]`.
+    InvalidTag,
+    /// The FEN tag does not hold a valid position.
+    InvalidFen(FenError),
+    /// A move is not legal or not in Standard Algebraic Notation.
+    InvalidMove(String, SanError),
+    /// A token that does not belong where it stands, e.g. a result inside a variation.
+    UnexpectedToken(String),
+    /// A variation is closed without being opened or the other way around.
+    UnbalancedVariation,
+    /// The moves of a game are not ended by a result such as "1-0" or "*".
+    MissingResult
+}
+
+/// The pieces a PGN file is made of, comments are skipped.
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    LeftBracket,
+    RightBracket,
+    LeftParen,
+    RightParen,
+    Period,
+    /// The result of a game still in progress.
+    Asterisk,
+    /// A Numeric Annotation Glyph such as `$1`.
+    Nag,
+    Str(String),
+    /// A move, move number, tag name or result.
+    Symbol(String)
+}
+
+/// Splits a PGN file into tokens, keeping track of line and column.
+struct Lexer {
+    chars: Vec<char>,
+    index: usize,
+    line: usize,
+    column: usize
+}
+
+/// Reads the games of a PGN file one token at a time.
+struct Parser {
+    lexer: Lexer,
+    /// A token that was looked at but not used yet, with its line and column.
+    peeked: Option<(Option<Token>, usize, usize)>
+}
+
+impl PgnGame {
+    /// Wrap a game with the Seven Tag Roster set to unknown values and
+    /// the result taken from the state of the game.
+    pub fn new(game: Game) -> PgnGame {
+        let mut pgn = PgnGame {tags: vec!(), game};
+        for name in SEVEN_TAG_ROSTER {
+            let value = match name {
+                "Date" => "????.??.??".to_string(),
+                "Result" => result_of(&pgn.game),
+                _ => "?".to_string()
+            };
+            pgn.set_tag(name, &value);
+        }
+        pgn
+    }
+
+    /// Read every game of a PGN file, in the order they appear.
+    pub fn read_all(pgn: &str) -> Result<Vec<PgnGame>, PgnError> {
+        let mut parser = Parser {lexer: Lexer::new(pgn), peeked: None};
+        let mut games = vec!();
+        while let Some(game) = parser.read_game()? {
+            games.push(game);
+        }
+        Ok(games)
+    }
+
+    /// Return the value of the tag with the given name, if it is set.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str())
+    }
+
+    /// Set the value of a tag, replacing an earlier value.
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some(tag) => tag.1 = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string()))
+        }
+    }
+
+    /// Return all tags in the order they were read or set.
+    pub fn tags(&self) -> &[(String, String)] {
+        &self.tags
+    }
+
+    /// Return the result tag: "1-0", "0-1", "1/2-1/2" or "*".
+    pub fn result(&self) -> &str {
+        self.tag("Result").unwrap_or("*")
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn into_game(self) -> Game {
+        self.game
+    }
+}
+
+impl Game {
+    /// Write the moves played so far in Portable Game Notation, with
+    /// unknown values for the tags other than the result.
+    pub fn to_pgn(&self) -> String {
+        PgnGame::new(self.clone()).to_string()
+    }
+
+    /// Read the first game of a PGN file. A result the moves do not decide
+    /// ends the game as if a player had resigned, agreed to a draw or run
+    /// out of time, so that it is written again by `to_pgn`.
+    pub fn from_pgn(pgn: &str) -> Result<Game, PgnError> {
+        let game: PgnGame = pgn.parse()?;
+        Ok(game.into_game())
+    }
+}
+
+impl FromStr for PgnGame {
+    type Err = PgnError;
+
+    /// Read the first game of a PGN file, an empty file is an error.
+    fn from_str(s: &str) -> Result<PgnGame, PgnError> {
+        let mut parser = Parser {lexer: Lexer::new(s), peeked: None};
+        match parser.read_game()? {
+            Some(game) => Ok(game),
+            None => Err(parser.error(PgnErrorKind::MissingResult))
+        }
+    }
+}
+
+impl fmt::Display for PgnGame {
+    /// Write the game in the PGN export format: the Seven Tag Roster,
+    /// the other tags, a FEN tag if the game did not start from the
+    /// initial position, and the moves in SAN followed by the result.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for name in SEVEN_TAG_ROSTER {
+            writeln!(f, "[{} \"{}\"]", name, escape(self.tag(name).unwrap_or("?")))?;
+        }
+
+        let start = start_position(&self.game);
+        let other_tags = self.tags.iter()
+            .filter(|(name, _)| !SEVEN_TAG_ROSTER.contains(&name.as_str()) && name != "SetUp" && name != "FEN");
+        for (name, value) in other_tags {
+            writeln!(f, "[{} \"{}\"]", name, escape(value))?;
+        }
+
+        let mut replay = Game::from_position(start);
+        if start != Position::start() {
+            writeln!(f, "[SetUp \"1\"]")?;
+            writeln!(f, "[FEN \"{}\"]", replay.to_fen())?;
+        }
+        writeln!(f)?;
+
+        let mut tokens = vec!();
+        for (index, entry) in self.game.history.iter().enumerate() {
+            let position = &replay.position;
+            if position.active == Color::White {
+                tokens.push(format!("{}.", position.fullmove_number));
+            }
+            else if index == 0 {
+                tokens.push(format!("{}...", position.fullmove_number));
+            }
+            tokens.push(replay.to_san(&entry.mv).expect("moves in the history are legal"));
+            replay.push_move(entry.mv);
+        }
+        tokens.push(self.result().to_string());
+
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() >= MAX_LINE_LENGTH {
+                writeln!(f, "{}", line)?;
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        writeln!(f, "{}", line)
+    }
+}
+
+impl PgnError {
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn kind(&self) -> &PgnErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for PgnErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PgnErrorKind::UnexpectedCharacter(c) => write!(f, "unexpected character '{}'", c),
+            PgnErrorKind::UnterminatedString => write!(f, "string or comment is not closed"),
+            PgnErrorKind::InvalidTag => write!(f, "tags must be written as [Name \"value\"]"),
+            PgnErrorKind::InvalidFen(error) => write!(f, "invalid FEN tag: {}", error),
+            PgnErrorKind::InvalidMove(san, error) => write!(f, "invalid move \"{}\": {}", san, error),
+            PgnErrorKind::UnexpectedToken(token) => write!(f, "unexpected \"{}\"", token),
+            PgnErrorKind::UnbalancedVariation => write!(f, "unbalanced parentheses around a variation"),
+            PgnErrorKind::MissingResult => write!(f, "the moves must end with a result")
+        }
+    }
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.kind)
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::LeftBracket => write!(f, "["),
+            Token::RightBracket => write!(f, "]"),
+            Token::LeftParen => write!(f, "("),
+            Token::RightParen => write!(f, ")"),
+            Token::Period => write!(f, "."),
+            Token::Asterisk => write!(f, "*"),
+            Token::Nag => write!(f, "$"),
+            Token::Str(s) => write!(f, "\"{}\"", s),
+            Token::Symbol(s) => write!(f, "{}", s)
+        }
+    }
+}
+
+impl Lexer {
+    fn new(text: &str) -> Lexer {
+        Lexer {chars: text.chars().collect(), index: 0, line: 1, column: 1}
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.index += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        }
+        else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn error(&self, kind: PgnErrorKind) -> PgnError {
+        PgnError {line: self.line, column: self.column, kind}
+    }
+
+    /// Return the next token with its line and column, None at the end of the text.
+    fn next_token(&mut self) -> Result<Option<(Token, usize, usize)>, PgnError> {
+        loop {
+            let (line, column) = (self.line, self.column);
+            let c = match self.peek() {
+                Some(c) => c,
+                None => return Ok(None)
+            };
+
+            let token = match c {
+                _ if c.is_whitespace() => {
+                    self.bump();
+                    continue;
+                },
+                // a percent sign in the first column escapes the rest of the line
+                '%' if column == 1 => {
+                    self.skip_line();
+                    continue;
+                },
+                ';' => {
+                    self.skip_line();
+                    continue;
+                },
+                '{' => {
+                    while self.bump().ok_or(self.error(PgnErrorKind::UnterminatedString))? != '}' {}
+                    continue;
+                },
+                '"' => {
+                    self.bump();
+                    Token::Str(self.read_string()?)
+                },
+                '$' => {
+                    self.bump();
+                    if !self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                        return Err(self.error(PgnErrorKind::UnexpectedCharacter('$')));
+                    }
+                    while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                        self.bump();
+                    }
+                    Token::Nag
+                },
+                // suffix annotations belong to the move before and are skipped like NAGs
+                '!' | '?' => {
+                    while matches!(self.peek(), Some('!') | Some('?')) {
+                        self.bump();
+                    }
+                    Token::Nag
+                },
+                _ if c.is_ascii_alphanumeric() => {
+                    let mut symbol = String::new();
+                    while let Some(c) = self.peek().filter(|&c| c.is_ascii_alphanumeric() || "_+#=:-/".contains(c)) {
+                        symbol.push(c);
+                        self.bump();
+                    }
+                    Token::Symbol(symbol)
+                },
+                _ => {
+                    let token = match c {
+                        '[' => Token::LeftBracket,
+                        ']' => Token::RightBracket,
+                        '(' => Token::LeftParen,
+                        ')' => Token::RightParen,
+                        '.' => Token::Period,
+                        '*' => Token::Asterisk,
+                        _ => return Err(self.error(PgnErrorKind::UnexpectedCharacter(c)))
+                    };
+                    self.bump();
+                    token
+                }
+            };
+            return Ok(Some((token, line, column)));
+        }
+    }
+
+    fn skip_line(&mut self) {
+        while self.bump().is_some_and(|c| c != '\n') {}
+    }
+
+    /// Read a string after its opening quote, with `\"` and `\\` as escapes.
+    fn read_string(&mut self) -> Result<String, PgnError> {
+        let mut s = String::new();
+        loop {
+            match self.bump().ok_or(self.error(PgnErrorKind::UnterminatedString))? {
+                '"' => return Ok(s),
+                '\\' => s.push(self.bump().ok_or(self.error(PgnErrorKind::UnterminatedString))?),
+                c => s.push(c)
+            }
+        }
+    }
+}
+
+impl Parser {
+    fn peek(&mut self) -> Result<&Option<Token>, PgnError> {
+        if self.peeked.is_none() {
+            let (line, column) = (self.lexer.line, self.lexer.column);
+            self.peeked = Some(match self.lexer.next_token()? {
+                Some((token, line, column)) => (Some(token), line, column),
+                None => (None, line, column)
+            });
+        }
+        Ok(&self.peeked.as_ref().expect("a token was peeked").0)
+    }
+
+    /// Return the next token with its line and column, None at the end of the text.
+    fn next(&mut self) -> Result<(Option<Token>, usize, usize), PgnError> {
+        self.peek()?;
+        Ok(self.peeked.take().expect("a token was peeked"))
+    }
+
+    /// An error at the token that is looked at next.
+    fn error(&mut self, kind: PgnErrorKind) -> PgnError {
+        let (line, column) = match &self.peeked {
+            Some((_, line, column)) => (*line, *column),
+            None => (self.lexer.line, self.lexer.column)
+        };
+        PgnError {line, column, kind}
+    }
+
+    /// Read the next game, None if there are no more games.
+    fn read_game(&mut self) -> Result<Option<PgnGame>, PgnError> {
+        if self.peek()?.is_none() {
+            return Ok(None);
+        }
+
+        let mut tags: Vec<(String, String)> = vec!();
+        // where the value of the FEN tag stands, to point errors in the position there
+        let mut fen_at = (0, 0);
+        while self.peek()? == &Some(Token::LeftBracket) {
+            self.next()?;
+            let name = match self.next()? {
+                (Some(Token::Symbol(name)), _, _) => name,
+                (_, line, column) => return Err(PgnError {line, column, kind: PgnErrorKind::InvalidTag})
+            };
+            let value = match self.next()? {
+                (Some(Token::Str(value)), line, column) => {
+                    if name == "FEN" {
+                        fen_at = (line, column);
+                    }
+                    value
+                },
+                (_, line, column) => return Err(PgnError {line, column, kind: PgnErrorKind::InvalidTag})
+            };
+            let (token, line, column) = self.next()?;
+            if token != Some(Token::RightBracket) {
+                return Err(PgnError {line, column, kind: PgnErrorKind::InvalidTag});
+            }
+            tags.push((name, value));
+        }
+
+        let fen = tags.iter().find(|(name, _)| name == "FEN").map(|(_, fen)| fen.clone());
+        let mut game = match fen {
+            Some(fen) => Game::from_fen(&fen).map_err(|error| {
+                PgnError {line: fen_at.0, column: fen_at.1, kind: PgnErrorKind::InvalidFen(error)}
+            })?,
+            None => Game::new()
+        };
+        let result = self.read_moves(&mut game, false)?.expect("the main line ends with a result");
+        if game.outcome().is_none() {
+            let timeout = tags.iter().any(|(name, value)| name == "Termination" && value == "time forfeit");
+            end_with_result(&mut game, &result, timeout);
+        }
+
+        let mut pgn = PgnGame {tags, game};
+        if pgn.tag("Result").is_none() {
+            pgn.set_tag("Result", &result);
+        }
+        Ok(Some(pgn))
+    }
+
+    /// Play the moves up to the result of the game, or up to the closing
+    /// parenthesis for a variation. Return the result of the game.
+    fn read_moves(&mut self, game: &mut Game, variation: bool) -> Result<Option<String>, PgnError> {
+        loop {
+            let (token, line, column) = self.next()?;
+            let error = |kind| PgnError {line, column, kind};
+            match token {
+                None if variation => return Err(error(PgnErrorKind::UnbalancedVariation)),
+                None => return Err(error(PgnErrorKind::MissingResult)),
+                Some(Token::Period) | Some(Token::Nag) => {},
+                Some(Token::Asterisk) if !variation => return Ok(Some("*".to_string())),
+                Some(Token::Symbol(symbol)) if symbol.chars().all(|c| c.is_ascii_digit()) => {},
+                Some(Token::Symbol(symbol)) if ["1-0", "0-1", "1/2-1/2"].contains(&symbol.as_str()) => {
+                    if variation {
+                        return Err(error(PgnErrorKind::UnexpectedToken(symbol)));
+                    }
+                    return Ok(Some(symbol));
+                },
+                Some(Token::Symbol(san)) => {
+                    let mv = game.parse_san(&san).map_err(|e| error(PgnErrorKind::InvalidMove(san.clone(), e)))?;
+                    game.push_move(mv);
+                },
+                // a variation replaces the move before it
+                Some(Token::LeftParen) => {
+                    let mut alternative = game.clone();
+                    if alternative.undo_move().is_none() {
+                        return Err(error(PgnErrorKind::UnexpectedToken("(".to_string())));
+                    }
+                    self.read_moves(&mut alternative, true)?;
+                },
+                Some(Token::RightParen) if variation => return Ok(None),
+                Some(Token::RightParen) => return Err(error(PgnErrorKind::UnbalancedVariation)),
+                Some(token) => return Err(error(PgnErrorKind::UnexpectedToken(token.to_string())))
+            }
+        }
+    }
+}
+
+/// Return the result of a game as written in PGN.
+fn result_of(game: &Game) -> String {
+    match game.outcome() {
+        Some(outcome) => outcome.to_string(),
+        None => "*".to_string()
+    }
+}
+
+/// End a game the moves did not decide with its result, which came about
+/// by resignation or agreement unless a player ran out of time.
+fn end_with_result(game: &mut Game, result: &str, timeout: bool) {
+    let winner = match result {
+        "1-0" => Some(Color::White),
+        "0-1" => Some(Color::Black),
+        "1/2-1/2" => None,
+        _ => return
+    };
+    let reason = match winner {
+        _ if timeout => Termination::Timeout,
+        Some(_) => Termination::Resignation,
+        None => Termination::Agreement
+    };
+    game.end(winner, reason).expect("the game is not over");
+}
+
+/// Return the position the game started from, before the first move in its history.
+fn start_position(game: &Game) -> Position {
+    let mut position = game.position;
+    for entry in game.history.iter().rev() {
+        position.unapply_move(entry.mv.from(), entry.mv.to(), &entry.undo);
+    }
+    position
+}
+
+/// Escape quotes and backslashes in a tag value.
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PgnErrorKind, PgnGame};
+    use crate::{Color, FenError, Game, Termination, GameState, SanError};
+
+    const SCHOLARS_MATE: &str = "\
+[Event \"Club \\\"Blitz\\\"\"]
+[Site \"?\"]
+[Date \"????.??.??\"]
+[Round \"?\"]
+[White \"?\"]
+[Black \"?\"]
+[Result \"1-0\"]
+
+1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# 1-0
+";
+
+    #[test]
+    fn game_is_written() {
+        let mut game = Game::new();
+        for san in ["e4", "e5", "Bc4", "Nc6", "Qh5", "Nf6", "Qxf7#"] {
+            let mv = game.parse_san(san).unwrap();
+            game.play(mv).unwrap();
+        }
+        let mut pgn = PgnGame::new(game);
+        assert_eq!(pgn.result(), "1-0");
+        pgn.set_tag("Event", "Club \"Blitz\"");
+        assert_eq!(pgn.to_string(), SCHOLARS_MATE);
+    }
+
+    #[test]
+    fn game_from_position_is_written() {
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4p3/K7 b - - 0 30").unwrap();
+        game.play("e2e1q".parse().unwrap()).unwrap();
+        let pgn = game.to_pgn();
+        assert!(pgn.contains("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4p3/K7 b - - 0 30\"]\n"));
+        assert!(pgn.ends_with("\n\n30... e1=Q+ *\n"));
+    }
+
+    #[test]
+    fn long_games_are_wrapped() {
+        let mut game = Game::new();
+        for (white, black) in [('3', '6'), ('4', '5')] {
+            for file in "abcdefgh".chars() {
+                for rank in [white, black] {
+                    let mv = game.parse_san(&format!("{}{}", file, rank)).unwrap();
+                    game.play(mv).unwrap();
+                }
+            }
+        }
+        let pgn = game.to_pgn();
+        assert!(pgn.lines().all(|line| line.len() < 80));
+        assert_eq!(Game::from_pgn(&pgn).unwrap().history().len(), 32);
+    }
+
+    #[test]
+    fn written_games_are_read_back() {
+        let pgn: PgnGame = SCHOLARS_MATE.parse().unwrap();
+        assert_eq!(pgn.tag("Event"), Some("Club \"Blitz\""));
+        assert_eq!(pgn.result(), "1-0");
+        assert_eq!(pgn.game().history().len(), 7);
+        let mate = GameState::GameOver {winner: Some(Color::White), reason: Termination::Checkmate};
+        assert_eq!(pgn.game().get_game_state(), mate);
+        assert_eq!(pgn.to_string(), SCHOLARS_MATE);
+    }
+
+    #[test]
+    fn results_off_the_board_are_kept() {
+        for (result, termination) in [("1-0", Termination::Resignation), ("0-1", Termination::Resignation),
+                ("1/2-1/2", Termination::Agreement)] {
+            let game = Game::from_pgn(&format!("[Result \"{}\"]\n\n1. e4 e5 {}", result, result)).unwrap();
+            assert_eq!(game.outcome().map(|outcome| outcome.to_string()), Some(result.to_string()));
+            assert_eq!(game.termination(), Some(termination));
+            let pgn = game.to_pgn();
+            assert!(pgn.contains(&format!("[Result \"{}\"]", result)), "{}", pgn);
+            assert!(pgn.ends_with(&format!("\n1. e4 e5 {}\n", result)), "{}", pgn);
+            assert_eq!(Game::from_pgn(&pgn).unwrap().outcome(), game.outcome());
+        }
+
+        let game = Game::from_pgn("[Termination \"time forfeit\"]\n\n1. e4 0-1").unwrap();
+        assert_eq!(game.termination(), Some(Termination::Timeout));
+        assert_eq!(Game::from_pgn("1. e4 *").unwrap().outcome(), None);
+    }
+
+    #[test]
+    fn annotations_and_variations_are_read() {
+        let text = "\
+% exported by hand
+[Event \"Casual\"]
+[White \"A\"]
+
+{Opening} 1. e4! e5 $1 (1... c5 2. Nf3 (2. c3) d6; Sicilian
+) 2. Nf3?! {A long
+comment} 2... Nc6 *
+
+[Event \"Second\"]
+[SetUp \"1\"]
+[FEN \"4k3/8/8/8/8/8/8/4K2R w K - 0 1\"]
+
+1. O-O Kd7 1/2-1/2
+";
+        let games = PgnGame::read_all(text).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].tag("White"), Some("A"));
+        assert_eq!(games[0].result(), "*");
+        assert_eq!(games[0].game().to_fen(), "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+        assert_eq!(games[1].tag("Event"), Some("Second"));
+        assert_eq!(games[1].result(), "1/2-1/2");
+        assert_eq!(games[1].game().to_fen(), "8/3k4/8/8/8/8/8/5RK1 w - - 2 2");
+        assert_eq!(PgnGame::read_all("").unwrap().len(), 0);
+    }
+
+    #[test]
+    fn errors_have_line_and_column() {
+        let errors = [
+            ("1. e4 e5\n2. Ke3 *", 2, 4, PgnErrorKind::InvalidMove("Ke3".to_string(), SanError::IllegalMove)),
+            ("[Event Casual]\n*", 1, 8, PgnErrorKind::InvalidTag),
+            ("[Event \"Casual]\n*", 2, 2, PgnErrorKind::UnterminatedString),
+            ("1. e4 {comment", 1, 15, PgnErrorKind::UnterminatedString),
+            ("1. e4 e5", 1, 9, PgnErrorKind::MissingResult),
+            ("1. e4 (1. d4 1-0) *", 1, 14, PgnErrorKind::UnexpectedToken("1-0".to_string())),
+            ("1. e4 (1. d4 *", 1, 14, PgnErrorKind::UnexpectedToken("*".to_string())),
+            ("1. e4 ) *", 1, 7, PgnErrorKind::UnbalancedVariation),
+            ("(1. e4) *", 1, 1, PgnErrorKind::UnexpectedToken("(".to_string())),
+            ("1. e4 & *", 1, 7, PgnErrorKind::UnexpectedCharacter('&')),
+            ("[Event \"Casual\"]\n[FEN \"bad fen\"]\n\n1. e4 *", 2, 6, PgnErrorKind::InvalidFen(FenError::WrongFieldCount))
+        ];
+        for (text, line, column, kind) in errors {
+            let error = PgnGame::read_all(text).unwrap_err();
+            assert_eq!((error.line(), error.column(), error.kind()), (line, column, &kind), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,542 @@
+use crate::{Color, File, Move, MoveList, Piece, Promotion, Rank, Square};
+use crate::bitboard::{self, Bitboard, KING_ATTACKS, KNIGHT_ATTACKS, LIGHT_SQUARES};
+use crate::zobrist;
+
+/// The pieces on the board together with everything else that decides which
+/// moves are possible. Unlike `Game` it is cheap to copy, which is used to
+/// try out moves when checking whether they leave the own king in check,
+/// and by the search. Evaluators look at it through its public methods.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Position {
+    /// `pieces[kind]` holds the squares of the pieces of one kind, of both
+    /// colours, with the kinds in the order of `KING` up to `PAWN`.
+    pieces: [Bitboard; 6],
+    /// `colors[0]` holds the squares of the white pieces, `colors[1]` those of the black pieces.
+    colors: [Bitboard; 2],
+    /// The Zobrist key of the pieces alone, updated by `set_piece`.
+    /// `hash` adds the rest of the position.
+    piece_key: u64,
+    pub(crate) active: Color,
+    pub(crate) castling: CastlingRights,
+    /// The square a pawn skipped with a double step in the last move,
+    /// where it can be captured en passant.
+    pub(crate) en_passant: Option<Square>,
+    /// Number of half moves since the last capture or pawn move.
+    pub(crate) halfmove_clock: u32,
+    /// Number of the current full move, starting at 1 and
+    /// increased after every move by Black.
+    pub(crate) fullmove_number: u32
+}
+
+/// What `Position::apply_move` changed besides the moved piece,
+/// so that the move can be taken back.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct Undo {
+    /// The piece that moved, before a promotion.
+    pub(crate) piece: Option<Piece>,
+    /// The piece that was captured, also en passant.
+    pub(crate) captured: Option<Piece>,
+    pub(crate) castling: CastlingRights,
+    pub(crate) en_passant: Option<Square>,
+    pub(crate) halfmove_clock: u32,
+    pub(crate) fullmove_number: u32
+}
+
+/// Which castling moves are still allowed. A right is lost as soon as
+/// the king or the corresponding rook moves or the rook is captured.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct CastlingRights {
+    pub(crate) white_king_side: bool,
+    pub(crate) white_queen_side: bool,
+    pub(crate) black_king_side: bool,
+    pub(crate) black_queen_side: bool
+}
+
+/// Indices of the kinds of pieces in `Position::pieces`.
+pub(crate) const KING: usize = 0;
+pub(crate) const QUEEN: usize = 1;
+pub(crate) const ROOK: usize = 2;
+pub(crate) const KNIGHT: usize = 3;
+pub(crate) const BISHOP: usize = 4;
+pub(crate) const PAWN: usize = 5;
+
+/// The pieces on the first and eighth rank at the start, from a to h.
+const BACK_RANK: [fn(Color) -> Piece; 8] = [
+    Piece::Rook, Piece::Knight, Piece::Bishop, Piece::Queen,
+    Piece::King, Piece::Bishop, Piece::Knight, Piece::Rook
+];
+
+impl CastlingRights {
+    /// Return the king side and queen side rights of the given colour.
+    pub(crate) fn get(&self, color: Color) -> (bool, bool) {
+        match color {
+            Color::White => (self.white_king_side, self.white_queen_side),
+            Color::Black => (self.black_king_side, self.black_queen_side)
+        }
+    }
+
+    /// Remove the rights that depend on a king or rook standing on `square`,
+    /// called with both squares of every move.
+    fn remove_for(&mut self, square: Square) {
+        match (square.file(), square.rank()) {
+            (File::E, Rank::First) => {self.white_king_side = false; self.white_queen_side = false;},
+            (File::H, Rank::First) => self.white_king_side = false,
+            (File::A, Rank::First) => self.white_queen_side = false,
+            (File::E, Rank::Eighth) => {self.black_king_side = false; self.black_queen_side = false;},
+            (File::H, Rank::Eighth) => self.black_king_side = false,
+            (File::A, Rank::Eighth) => self.black_queen_side = false,
+            _ => {}
+        }
+    }
+}
+
+impl Position {
+    /// The position at the start of a game.
+    pub(crate) fn start() -> Position {
+        let mut position = Position::empty();
+        position.castling = CastlingRights {
+            white_king_side: true,
+            white_queen_side: true,
+            black_king_side: true,
+            black_queen_side: true
+        };
+        for (&file, piece) in File::ALL.iter().zip(BACK_RANK) {
+            position.set_piece(Square::new(file, Rank::First), Some(piece(Color::White)));
+            position.set_piece(Square::new(file, Rank::Second), Some(Piece::Pawn(Color::White)));
+            position.set_piece(Square::new(file, Rank::Seventh), Some(Piece::Pawn(Color::Black)));
+            position.set_piece(Square::new(file, Rank::Eighth), Some(piece(Color::Black)));
+        }
+        position
+    }
+
+    /// A position without any pieces, White to move and no castling rights.
+    pub(crate) fn empty() -> Position {
+        Position {
+            pieces: [0; 6],
+            colors: [0; 2],
+            piece_key: 0,
+            active: Color::White,
+            castling: CastlingRights {
+                white_king_side: false,
+                white_queen_side: false,
+                black_king_side: false,
+                black_queen_side: false
+            },
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1
+        }
+    }
+
+    /// Return the piece on the square, if there is one.
+    pub fn piece_at(&self, square: Square) -> Option<Piece> {
+        self.get_piece(square)
+    }
+
+    /// Return the colour whose turn it is.
+    pub fn active_color(&self) -> Color {
+        self.active
+    }
+
+    /// Return true if the king of the side to move is attacked.
+    pub fn is_check(&self) -> bool {
+        self.in_check(self.active)
+    }
+
+    /// Return the piece on the square, if there is one
+    pub(crate) fn get_piece(&self, square: Square) -> Option<Piece> {
+        let bit = bitboard::bit(square);
+        let color = if self.colors[0] & bit != 0 {
+            Color::White
+        }
+        else if self.colors[1] & bit != 0 {
+            Color::Black
+        }
+        else {
+            return None;
+        };
+        let kind = (KING..=PAWN).find(|&kind| self.pieces[kind] & bit != 0)?;
+        Some(piece_of_kind(kind, color))
+    }
+
+    /// Put `piece` on the square, or empty it if `piece` is None.
+    pub(crate) fn set_piece(&mut self, square: Square, piece: Option<Piece>) {
+        if let Some(old) = self.get_piece(square) {
+            self.piece_key ^= zobrist::piece_key(old, square);
+        }
+        let bit = bitboard::bit(square);
+        for set in self.pieces.iter_mut().chain(self.colors.iter_mut()) {
+            *set &= !bit;
+        }
+        if let Some(piece) = piece {
+            self.piece_key ^= zobrist::piece_key(piece, square);
+            self.pieces[kind_index(piece)] |= bit;
+            self.colors[color_index(piece.color())] |= bit;
+        }
+    }
+
+    /// Return the squares of the pieces of the given kind and colour,
+    /// e.g. all white knights for `Piece::Knight(Color::White)`.
+    pub(crate) fn pieces_of(&self, piece: Piece) -> Bitboard {
+        self.pieces[kind_index(piece)] & self.colors[color_index(piece.color())]
+    }
+
+    /// Return the squares of all pieces of the given colour.
+    pub(crate) fn pieces_of_color(&self, color: Color) -> Bitboard {
+        self.colors[color_index(color)]
+    }
+
+    /// Return the squares of all pieces.
+    pub(crate) fn occupied(&self) -> Bitboard {
+        self.colors[0] | self.colors[1]
+    }
+
+    /// Return the board as an array seen from White, see `board_index`.
+    pub(crate) fn board(&self) -> [[Option<Piece>; 8]; 8] {
+        let mut board = [[None; 8]; 8];
+        for square in Square::all() {
+            let (row, col) = board_index(square);
+            board[row][col] = self.get_piece(square);
+        }
+        board
+    }
+
+    /// Move the piece at `from` to `to` without checking any rules and
+    /// hand the turn to the other side. A pawn reaching the last rank
+    /// becomes `promotion`. Return what is needed to take the move back.
+    pub(crate) fn apply_move(&mut self, from: Square, to: Square, promotion: Promotion) -> Undo {
+        let piece = self.get_piece(from);
+        let en_passant_capture = matches!(piece, Some(Piece::Pawn(_)))
+            && Some(to) == self.en_passant && from.file() != to.file();
+        let undo = Undo {
+            piece,
+            captured: if en_passant_capture {
+                self.get_piece(Square::new(to.file(), from.rank()))
+            } else {
+                self.get_piece(to)
+            },
+            castling: self.castling,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number
+        };
+        let capture = undo.captured.is_some();
+        self.set_piece(from, None);
+
+        // captures and pawn moves reset the clock, black moving completes a full move
+        self.halfmove_clock = match piece {
+            Some(Piece::Pawn(_)) => 0,
+            _ if capture => 0,
+            _ => self.halfmove_clock + 1
+        };
+        if piece.is_some_and(|piece| piece.color() == Color::Black) {
+            self.fullmove_number += 1;
+        }
+
+        // a king moving two squares is castling, so the rook jumps over it
+        if let Some(Piece::King(_)) = piece {
+            if let Some((rook_from, rook_to)) = castling_rook_squares(from, to) {
+                let rook = self.get_piece(rook_from);
+                self.set_piece(rook_from, None);
+                self.set_piece(rook_to, rook);
+            }
+        }
+
+        // a pawn moving diagonally to the en passant square captures the pawn beside it
+        if en_passant_capture {
+            self.set_piece(Square::new(to.file(), from.rank()), None);
+        }
+        self.en_passant = match piece {
+            Some(Piece::Pawn(_)) if from.rank().index().abs_diff(to.rank().index()) == 2 => {
+                Square::from_index((from.index() + to.index()) / 2)
+            },
+            _ => None
+        };
+
+        self.set_piece(to, match piece {
+            Some(Piece::Pawn(color)) if to.rank() == Rank::First || to.rank() == Rank::Eighth => {
+                Some(promotion.to_piece(color))
+            },
+            _ => piece
+        });
+        self.castling.remove_for(from);
+        self.castling.remove_for(to);
+        self.active = self.active.opposite();
+        undo
+    }
+
+    /// Take back the move from `from` to `to`, which must be the last move
+    /// made with `apply_move`, returning the `undo` it gave.
+    pub(crate) fn unapply_move(&mut self, from: Square, to: Square, undo: &Undo) {
+        self.active = self.active.opposite();
+        self.castling = undo.castling;
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.fullmove_number = undo.fullmove_number;
+
+        self.set_piece(from, undo.piece);
+        let en_passant_capture = matches!(undo.piece, Some(Piece::Pawn(_)))
+            && Some(to) == undo.en_passant && from.file() != to.file();
+        if en_passant_capture {
+            self.set_piece(to, None);
+            self.set_piece(Square::new(to.file(), from.rank()), undo.captured);
+        }
+        else {
+            self.set_piece(to, undo.captured);
+        }
+
+        // put the rook back to its corner after castling
+        if let Some(Piece::King(_)) = undo.piece {
+            if let Some((rook_from, rook_to)) = castling_rook_squares(from, to) {
+                let rook = self.get_piece(rook_to);
+                self.set_piece(rook_to, None);
+                self.set_piece(rook_from, rook);
+            }
+        }
+    }
+
+    /// Return true if the king of the given colour is attacked.
+    pub(crate) fn in_check(&self, color: Color) -> bool {
+        match self.find_king(color) {
+            Some(square) => self.is_attacked(square, color.opposite()),
+            None => false
+        }
+    }
+
+    /// Return true if the given colour has at least one legal move.
+    pub(crate) fn has_legal_move(&self, color: Color) -> bool {
+        bitboard::squares(self.pieces_of_color(color)).any(|square| !self.moves_from(square).is_empty())
+    }
+
+    /// Return true if moving the piece at `from` to `to` would leave
+    /// the king of the moving side in check.
+    fn leaves_king_in_check(&self, from: Square, to: Square) -> bool {
+        let color = match self.get_piece(from) {
+            Some(piece) => piece.color(),
+            None => return false
+        };
+        let mut after = *self;
+        after.apply_move(from, to, Promotion::Queen);
+        after.in_check(color)
+    }
+
+    /// Return the square of the king of the given colour.
+    fn find_king(&self, color: Color) -> Option<Square> {
+        bitboard::first_square(self.pieces_of(Piece::King(color)))
+    }
+
+    /// Return true if any piece of colour `by` attacks `square`.
+    pub(crate) fn is_attacked(&self, square: Square, by: Color) -> bool {
+        let them = self.pieces_of_color(by);
+        let occupied = self.occupied();
+        let queens = self.pieces[QUEEN];
+
+        // a pawn of `by` attacks the square if a pawn of the other colour on the square would attack it
+        bitboard::pawn_attacks(by.opposite(), square) & self.pieces[PAWN] & them != 0
+            || KNIGHT_ATTACKS[square.index()] & self.pieces[KNIGHT] & them != 0
+            || KING_ATTACKS[square.index()] & self.pieces[KING] & them != 0
+            || bitboard::bishop_attacks(square, occupied) & (self.pieces[BISHOP] | queens) & them != 0
+            || bitboard::rook_attacks(square, occupied) & (self.pieces[ROOK] | queens) & them != 0
+    }
+
+    /// Return all legal moves of the piece on `square`, whichever colour it
+    /// has. A pawn reaching the last rank gives one move for every promotion.
+    pub(crate) fn moves_from(&self, square: Square) -> MoveList {
+        let mut moves = MoveList::new();
+        self.push_moves_from(square, &mut moves);
+        moves
+    }
+
+    /// Add the legal moves of the piece on `square` to `moves`.
+    fn push_moves_from(&self, square: Square, moves: &mut MoveList) {
+        for to in bitboard::squares(self.pseudo_targets(square)) {
+            if self.leaves_king_in_check(square, to) {
+                continue;
+            }
+            match self.get_piece(square) {
+                Some(Piece::Pawn(_)) if to.rank() == Rank::First || to.rank() == Rank::Eighth => {
+                    for promotion in Promotion::ALL {
+                        moves.push(Move::with_promotion(square, to, promotion));
+                    }
+                },
+                _ => moves.push(Move::new(square, to))
+            }
+        }
+    }
+
+    /// Return all legal moves of the side to move.
+    pub fn legal_moves(&self) -> MoveList {
+        let mut moves = MoveList::new();
+        for square in bitboard::squares(self.pieces_of_color(self.active)) {
+            self.push_moves_from(square, &mut moves);
+        }
+        moves
+    }
+
+    /// Return true if the move captures a piece, also en passant.
+    pub(crate) fn is_capture(&self, mv: &Move) -> bool {
+        self.get_piece(mv.to()).is_some()
+            || (matches!(self.get_piece(mv.from()), Some(Piece::Pawn(_))) && Some(mv.to()) == self.en_passant)
+    }
+
+    /// Return true if the legal move puts the king of the other side in check.
+    pub(crate) fn gives_check(&self, mv: &Move) -> bool {
+        let mut after = *self;
+        after.apply_move(mv.from(), mv.to(), mv.promotion().unwrap_or(Promotion::Queen));
+        after.in_check(after.active)
+    }
+
+    /// Return the position with only what decides whether positions repeat:
+    /// the clocks are cleared and the en passant square is kept only if a
+    /// pawn can actually capture there.
+    pub(crate) fn repetition_key(&self) -> Position {
+        let en_passant = self.en_passant.filter(|&square| {
+            self.legal_moves().iter()
+                .any(|mv| mv.to() == square && matches!(self.get_piece(mv.from()), Some(Piece::Pawn(_))))
+        });
+        Position {en_passant, halfmove_clock: 0, fullmove_number: 0, ..*self}
+    }
+
+    /// Return the Polyglot compatible Zobrist key of the position, see `Game::hash`.
+    pub fn hash(&self) -> u64 {
+        let mut key = self.piece_key
+            ^ zobrist::castling_key(Color::White, self.castling.get(Color::White))
+            ^ zobrist::castling_key(Color::Black, self.castling.get(Color::Black))
+            ^ zobrist::turn_key(self.active);
+        if let Some(square) = self.en_passant {
+            // a pawn of the side to move would capture from where a pawn of the other side attacks
+            let capturers = bitboard::pawn_attacks(self.active.opposite(), square) & self.pieces_of(Piece::Pawn(self.active));
+            if capturers != 0 {
+                key ^= zobrist::en_passant_key(square.file());
+            }
+        }
+        key
+    }
+
+    /// Return true if neither side can checkmate whatever is played: only
+    /// the kings and a single knight or bishop are left, or only bishops
+    /// that all stand on squares of the same colour.
+    pub(crate) fn insufficient_material(&self) -> bool {
+        if self.pieces[PAWN] | self.pieces[ROOK] | self.pieces[QUEEN] != 0 {
+            return false;
+        }
+        let bishops = self.pieces[BISHOP];
+        (self.pieces[KNIGHT] | bishops).count_ones() <= 1
+            || (self.pieces[KNIGHT] == 0 && (bishops & LIGHT_SQUARES == 0 || bishops & !LIGHT_SQUARES == 0))
+    }
+
+    /// Return all squares the piece on `square` can reach,
+    /// without considering whether the own king is left in check.
+    fn pseudo_targets(&self, square: Square) -> Bitboard {
+        let piece = match self.get_piece(square) {
+            Some(piece) => piece,
+            None => return 0
+        };
+        let occupied = self.occupied();
+        let targets = match piece {
+            Piece::Pawn(color) => return self.pawn_targets(square, color),
+            Piece::Knight(_) => KNIGHT_ATTACKS[square.index()],
+            Piece::Bishop(_) => bitboard::bishop_attacks(square, occupied),
+            Piece::Rook(_) => bitboard::rook_attacks(square, occupied),
+            Piece::Queen(_) => bitboard::bishop_attacks(square, occupied) | bitboard::rook_attacks(square, occupied),
+            Piece::King(color) => KING_ATTACKS[square.index()] | self.castling_targets(square, color)
+        };
+        targets & !self.pieces_of_color(piece.color())
+    }
+
+    /// Castling moves for the king on the given square. The king may not castle
+    /// out of or through check, moving into check is left to the legality filter.
+    fn castling_targets(&self, square: Square, color: Color) -> Bitboard {
+        let rank = if color == Color::White {Rank::First} else {Rank::Eighth};
+        if square != Square::new(File::E, rank) || self.is_attacked(square, color.opposite()) {
+            return 0;
+        }
+
+        let (king_side, queen_side) = self.castling.get(color);
+        let rook = Some(Piece::Rook(color));
+        let on_rank = |file: File| Square::new(file, rank);
+        let empty = |files: &[File]| files.iter().all(|&file| self.occupied() & bitboard::bit(on_rank(file)) == 0);
+
+        let mut targets = 0;
+        if king_side && self.get_piece(on_rank(File::H)) == rook && empty(&[File::F, File::G])
+            && !self.is_attacked(on_rank(File::F), color.opposite()) {
+            targets |= bitboard::bit(on_rank(File::G));
+        }
+        if queen_side && self.get_piece(on_rank(File::A)) == rook && empty(&[File::B, File::C, File::D])
+            && !self.is_attacked(on_rank(File::D), color.opposite()) {
+            targets |= bitboard::bit(on_rank(File::C));
+        }
+        targets
+    }
+
+    /// Pushes, double steps from the starting rank, captures and en passant
+    /// captures of the pawn of the given colour on `square`.
+    fn pawn_targets(&self, square: Square, color: Color) -> Bitboard {
+        let (forward, start_rank) = if color == Color::White {(1, Rank::Second)} else {(-1, Rank::Seventh)};
+        let occupied = self.occupied();
+        let mut targets = 0;
+
+        if let Some(one_step) = square.offset(0, forward).filter(|&one_step| occupied & bitboard::bit(one_step) == 0) {
+            targets |= bitboard::bit(one_step);
+            if let Some(two_steps) = one_step.offset(0, forward) {
+                if square.rank() == start_rank && occupied & bitboard::bit(two_steps) == 0 {
+                    targets |= bitboard::bit(two_steps);
+                }
+            }
+        }
+
+        let en_passant = self.en_passant.map_or(0, bitboard::bit);
+        targets | bitboard::pawn_attacks(color, square) & (self.pieces_of_color(color.opposite()) | en_passant)
+    }
+}
+
+/// Return the index of the kind of the piece in `Position::pieces`.
+pub(crate) fn kind_index(piece: Piece) -> usize {
+    match piece {
+        Piece::King(_) => KING,
+        Piece::Queen(_) => QUEEN,
+        Piece::Rook(_) => ROOK,
+        Piece::Knight(_) => KNIGHT,
+        Piece::Bishop(_) => BISHOP,
+        Piece::Pawn(_) => PAWN
+    }
+}
+
+/// Return the piece of the kind with the given index and colour.
+fn piece_of_kind(kind: usize, color: Color) -> Piece {
+    match kind {
+        KING => Piece::King(color),
+        QUEEN => Piece::Queen(color),
+        ROOK => Piece::Rook(color),
+        KNIGHT => Piece::Knight(color),
+        BISHOP => Piece::Bishop(color),
+        _ => Piece::Pawn(color)
+    }
+}
+
+/// Return the index of the colour in `Position::colors`.
+pub(crate) fn color_index(color: Color) -> usize {
+    match color {
+        Color::White => 0,
+        Color::Black => 1
+    }
+}
+
+/// Return the squares the rook jumps from and to if a king moving from
+/// `from` to `to` castles.
+fn castling_rook_squares(from: Square, to: Square) -> Option<(Square, Square)> {
+    let rank = from.rank();
+    match (from.file(), to.file()) {
+        (File::E, File::G) => Some((Square::new(File::H, rank), Square::new(File::F, rank))),
+        (File::E, File::C) => Some((Square::new(File::A, rank), Square::new(File::D, rank))),
+        _ => None
+    }
+}
+
+/// Return `(row, col)` such that `board[row][col]` is the given square.
+/// Rows run from the eighth rank down to the first, so that the array
+/// reads like the board seen from White, and columns from a to h,
+/// e.g. a8 is `board[0][0]`, h8 `board[0][7]` and a1 `board[7][0]`.
+pub(crate) fn board_index(square: Square) -> (usize, usize) {
+    (7 - square.rank().index(), square.file().index())
+}
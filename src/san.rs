@@ -0,0 +1,289 @@
+use std::fmt;
+use std::mem::discriminant;
+
+use crate::{File, Game, Move, Piece, Promotion, Rank, Square};
+use crate::position::Position;
+
+/// Why a move in Standard Algebraic Notation could not be read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SanError {
+    /// The string is not written in SAN, e.g. "Nz9".
+    InvalidSyntax,
+    /// No legal move matches the string.
+    IllegalMove,
+    /// Several legal moves match the string, e.g. "Nd2" when both knights can go there.
+    AmbiguousMove
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            SanError::InvalidSyntax => "not a move in algebraic notation",
+            SanError::IllegalMove => "no legal move matches",
+            SanError::AmbiguousMove => "more than one legal move matches"
+        };
+        write!(f, "{}", message)
+    }
+}
+
+impl std::error::Error for SanError {}
+
+impl Game {
+    /// Read a move in Standard Algebraic Notation, e.g. "e4", "Nxf3+",
+    /// "exd6", "R1a3", "O-O-O" or "e8=Q#", and return the legal move it
+    /// stands for in the current position. Check and mate suffixes as well
+    /// as annotations like "!?" are accepted but not checked.
+    pub fn parse_san(&self, san: &str) -> Result<Move, SanError> {
+        parse_san(&self.position, san)
+    }
+
+    /// Write a legal move in Standard Algebraic Notation, None if the move
+    /// is not legal in the current position.
+    pub fn to_san(&self, mv: &Move) -> Option<String> {
+        to_san(&self.position, mv)
+    }
+}
+
+/// The parts of a SAN move that are not castling.
+struct SanMove {
+    /// The kind of piece that moves, the colour is ignored.
+    piece: Piece,
+    from_file: Option<File>,
+    from_rank: Option<Rank>,
+    to: Square,
+    promotion: Option<Promotion>
+}
+
+pub(crate) fn parse_san(position: &Position, san: &str) -> Result<Move, SanError> {
+    let san = san.trim_end_matches(['+', '#', '!', '?']);
+    let color = position.active;
+    let legal_moves = position.legal_moves();
+
+    let matches: Vec<Move> = match san {
+        "O-O" | "0-0" | "O-O-O" | "0-0-0" => {
+            let file = if san.len() == 3 {File::G} else {File::C};
+            legal_moves.into_iter()
+                .filter(|mv| position.get_piece(mv.from()) == Some(Piece::King(color))
+                    && mv.from().file() == File::E && mv.to().file() == file)
+                .collect()
+        },
+        _ => {
+            let parsed = read_san(san).ok_or(SanError::InvalidSyntax)?;
+            legal_moves.into_iter()
+                .filter(|mv| {
+                    position.get_piece(mv.from()).is_some_and(|piece| discriminant(&piece) == discriminant(&parsed.piece))
+                        && mv.to() == parsed.to
+                        && mv.promotion() == parsed.promotion
+                        && parsed.from_file.is_none_or(|file| mv.from().file() == file)
+                        && parsed.from_rank.is_none_or(|rank| mv.from().rank() == rank)
+                })
+                .collect()
+        }
+    };
+
+    match matches.len() {
+        0 => Err(SanError::IllegalMove),
+        1 => Ok(matches[0]),
+        _ => Err(SanError::AmbiguousMove)
+    }
+}
+
+/// Split a SAN move that is not castling into its parts.
+fn read_san(san: &str) -> Option<SanMove> {
+    let mut chars: Vec<char> = san.chars().collect();
+
+    // the promotion comes last, with or without "="
+    let mut promotion = None;
+    if let Some(&last) = chars.last() {
+        if "QRBN".contains(last) && chars.len() > 2 {
+            promotion = Some(Promotion::from_name(&last.to_string())?);
+            chars.pop();
+            if chars.last() == Some(&'=') {
+                chars.pop();
+            }
+        }
+    }
+
+    let piece = match chars.first() {
+        Some(&c) if "KQRBN".contains(c) => {
+            chars.remove(0);
+            Piece::from_char(c)?
+        },
+        _ => Piece::Pawn(crate::Color::White)
+    };
+    if promotion.is_some() && !matches!(piece, Piece::Pawn(_)) {
+        return None;
+    }
+
+    if chars.len() < 2 {
+        return None;
+    }
+    let to: Square = chars.split_off(chars.len() - 2).iter().collect::<String>().parse().ok()?;
+    if chars.last() == Some(&'x') {
+        chars.pop();
+    }
+
+    let (from_file, from_rank) = match chars.as_slice() {
+        [] => (None, None),
+        [c] if c.is_ascii_digit() => (None, Some(Rank::from_char(*c)?)),
+        [c] => (Some(File::from_char(*c)?), None),
+        [file, rank] => (Some(File::from_char(*file)?), Some(Rank::from_char(*rank)?)),
+        _ => return None
+    };
+    // files must be lower case to keep them apart from the bishop
+    if chars.iter().any(|c| c.is_ascii_uppercase()) {
+        return None;
+    }
+
+    Some(SanMove {piece, from_file, from_rank, to, promotion})
+}
+
+pub(crate) fn to_san(position: &Position, mv: &Move) -> Option<String> {
+    let legal_moves = position.legal_moves();
+    if !legal_moves.contains(mv) {
+        return None;
+    }
+    let piece = position.get_piece(mv.from())?;
+    let mut san = String::new();
+
+    let castling = matches!(piece, Piece::King(_)) && mv.from().file() == File::E
+        && (mv.to().file() == File::G || mv.to().file() == File::C) && mv.from().rank() == mv.to().rank();
+    if castling {
+        san.push_str(if mv.to().file() == File::G {"O-O"} else {"O-O-O"});
+    }
+    else {
+        let capture = position.is_capture(mv);
+
+        if let Piece::Pawn(_) = piece {
+            if capture {
+                san.push(mv.from().file().to_char());
+            }
+        }
+        else {
+            san.push(piece.to_char().to_ascii_uppercase());
+
+            // name the file, the rank or both if another piece of the same kind can go there
+            let others: Vec<Square> = legal_moves.iter()
+                .filter(|other| other.to() == mv.to() && other.from() != mv.from()
+                    && position.get_piece(other.from()) == Some(piece))
+                .map(|other| other.from())
+                .collect();
+            if !others.is_empty() {
+                if others.iter().all(|other| other.file() != mv.from().file()) {
+                    san.push(mv.from().file().to_char());
+                }
+                else if others.iter().all(|other| other.rank() != mv.from().rank()) {
+                    san.push(mv.from().rank().to_char());
+                }
+                else {
+                    san.push_str(&mv.from().to_string());
+                }
+            }
+        }
+
+        if capture {
+            san.push('x');
+        }
+        san.push_str(&mv.to().to_string());
+        if let Some(promotion) = mv.promotion() {
+            san.push('=');
+            san.push(promotion.to_char().to_ascii_uppercase());
+        }
+    }
+
+    let mut after = *position;
+    after.apply_move(mv.from(), mv.to(), mv.promotion().unwrap_or(Promotion::Queen));
+    if after.in_check(after.active) {
+        san.push(if after.has_legal_move(after.active) {'+'} else {'#'});
+    }
+    Some(san)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SanError;
+    use crate::{Game, Move};
+
+    fn mv(s: &str) -> Move {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn simple_moves_are_written() {
+        let game = Game::new();
+        assert_eq!(game.to_san(&mv("e2e4")).unwrap(), "e4");
+        assert_eq!(game.to_san(&mv("g1f3")).unwrap(), "Nf3");
+        assert_eq!(game.to_san(&mv("e2e5")), None);
+    }
+
+    #[test]
+    fn special_moves_are_written() {
+        let castling = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(castling.to_san(&mv("e1g1")).unwrap(), "O-O");
+        assert_eq!(castling.to_san(&mv("e1c1")).unwrap(), "O-O-O");
+        assert_eq!(castling.to_san(&mv("a1a8")).unwrap(), "Rxa8+");
+
+        let en_passant = Game::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2").unwrap();
+        assert_eq!(en_passant.to_san(&mv("e5d6")).unwrap(), "exd6");
+
+        let promotion = Game::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(promotion.to_san(&mv("b7b8q")).unwrap(), "b8=Q+");
+        assert_eq!(promotion.to_san(&mv("b7b8n")).unwrap(), "b8=N");
+
+        let mate = Game::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        assert_eq!(mate.to_san(&mv("a1a8")).unwrap(), "Ra8#");
+    }
+
+    #[test]
+    fn ambiguous_moves_are_disambiguated() {
+        let game = Game::from_fen("2k5/8/8/8/Q6Q/8/Q3K3/R6R w - - 0 1").unwrap();
+        assert_eq!(game.to_san(&mv("h1f1")).unwrap(), "Rhf1");
+        assert_eq!(game.to_san(&mv("a1b1")).unwrap(), "Rab1");
+        assert_eq!(game.to_san(&mv("a4a3")).unwrap(), "Q4a3");
+        assert_eq!(game.to_san(&mv("a4e4")).unwrap(), "Qae4");
+        assert_eq!(game.to_san(&mv("h4h8")).unwrap(), "Qh8+");
+
+        let queens = Game::from_fen("6k1/8/8/8/Q2Q4/8/7K/Q7 w - - 0 1").unwrap();
+        assert_eq!(queens.to_san(&mv("a4d1")).unwrap(), "Qa4d1");
+    }
+
+    #[test]
+    fn san_is_parsed() {
+        let game = Game::new();
+        assert_eq!(game.parse_san("e4"), Ok(mv("e2e4")));
+        assert_eq!(game.parse_san("Nf3"), Ok(mv("g1f3")));
+        assert_eq!(game.parse_san("Ngf3!?"), Ok(mv("g1f3")));
+        assert_eq!(game.parse_san("e5"), Err(SanError::IllegalMove));
+        assert_eq!(game.parse_san("Nz9"), Err(SanError::InvalidSyntax));
+        assert_eq!(game.parse_san(""), Err(SanError::InvalidSyntax));
+
+        let knights = Game::from_fen("4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1").unwrap();
+        assert_eq!(knights.parse_san("Nd2"), Err(SanError::AmbiguousMove));
+        assert_eq!(knights.parse_san("Nbd2"), Ok(mv("b1d2")));
+
+        let promotion = Game::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(promotion.parse_san("b8=R"), Ok(mv("b7b8r")));
+        assert_eq!(promotion.parse_san("b8Q+"), Ok(mv("b7b8q")));
+        assert_eq!(promotion.parse_san("b8"), Err(SanError::IllegalMove));
+
+        let castling = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1").unwrap();
+        assert_eq!(castling.parse_san("O-O-O"), Ok(mv("e8c8")));
+        assert_eq!(castling.parse_san("0-0"), Ok(mv("e8g8")));
+    }
+
+    #[test]
+    fn every_legal_move_round_trips() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8"
+        ];
+        for fen in fens {
+            let game = Game::from_fen(fen).unwrap();
+            for legal in game.position.legal_moves() {
+                let san = game.to_san(&legal).unwrap();
+                assert_eq!(game.parse_san(&san), Ok(legal), "{} in {}", san, fen);
+            }
+        }
+    }
+}
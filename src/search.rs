@@ -0,0 +1,688 @@
+use std::cmp::Reverse;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::{Color, DefaultEvaluator, Evaluator, Game, Move, MoveList, Piece, Promotion};
+use crate::position::Position;
+use crate::transposition::{self, Bound, TranspositionTable};
+
+/// The deepest a search goes, in half moves from the root.
+const MAX_PLY: usize = 64;
+
+/// The score of being checkmated at the root; a mate found `n` half moves
+/// deeper scores `MATE - n` for the winning side.
+const MATE: i32 = 30_000;
+
+/// Larger than any score.
+const INFINITY: i32 = 32_000;
+
+/// How many nodes are searched between two looks at the clock.
+const CLOCK_INTERVAL: u64 = 1024;
+
+/// The size of the transposition table of `Game::best_move`, in megabytes,
+/// small as it is thrown away after a single search.
+const BEST_MOVE_MEGABYTES: usize = 1;
+
+/// The largest transposition table a `Searcher` uses, in megabytes.
+pub const MAX_HASH_MEGABYTES: usize = transposition::MAX_MEGABYTES;
+
+/// When a search stops. A search without any limit goes on up to the
+/// deepest depth it supports.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Limit {
+    /// The depth in half moves to search to.
+    pub depth: Option<u32>,
+    /// The number of positions to look at.
+    pub nodes: Option<u64>,
+    /// The time to think.
+    pub time: Option<Duration>
+}
+
+/// How good a position is for the side to move.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Score {
+    /// An advantage in hundredths of a pawn.
+    Centipawns(i32),
+    /// Mate in the given number of moves, negative if the side to move gets mated.
+    Mate(i32)
+}
+
+/// The best move a search found with what it is based on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchResult {
+    mv: Move,
+    score: Score,
+    pv: Vec<Move>,
+    depth: u32,
+    nodes: u64
+}
+
+/// Searches for the best move with iterative deepening alpha-beta, judging
+/// the positions it reaches with its `Evaluator`. What it learns about good
+/// moves and positions is kept from one search to the next, so reusing it
+/// over the moves of a game helps.
+#[derive(Clone)]
+pub struct Searcher<E = DefaultEvaluator> {
+    evaluator: E,
+    /// Two quiet moves per ply that recently caused a cutoff, the newer first.
+    killers: [[Option<Move>; 2]; MAX_PLY],
+    /// How often quiet moves caused a cutoff, weighted by depth, by from and to square.
+    history: [[i32; 64]; 64],
+    /// The results of searching positions, by their key.
+    table: TranspositionTable,
+    /// `pv[ply]` holds the best line found from the node at `ply` on.
+    pv: Vec<MoveList>,
+    /// The principal variation of the last finished iteration.
+    previous_pv: MoveList,
+    /// Whether the moves played so far follow `previous_pv`.
+    follow_pv: bool,
+    /// Keys of the positions of the game and of the line searched, the current one last.
+    keys: Vec<u64>,
+    nodes: u64,
+    limit: Limit,
+    start: Instant,
+    /// Set from outside to stop the search, see `stop_flag`.
+    stop: Arc<AtomicBool>,
+    /// Whether an iteration has finished, so that the search may stop.
+    can_stop: bool,
+    /// Whether a limit was hit and the search is unwinding.
+    stopped: bool
+}
+
+impl Limit {
+    /// Search to the given depth in half moves.
+    pub fn depth(depth: u32) -> Limit {
+        Limit {depth: Some(depth), ..Limit::default()}
+    }
+
+    /// Search about the given number of positions.
+    pub fn nodes(nodes: u64) -> Limit {
+        Limit {nodes: Some(nodes), ..Limit::default()}
+    }
+
+    /// Search for at most the given time.
+    pub fn time(time: Duration) -> Limit {
+        Limit {time: Some(time), ..Limit::default()}
+    }
+
+    /// Search for a share of the time left on the clock, `remaining`, when
+    /// `increment` is added after every move and `moves_to_go` moves are to
+    /// be made until more time is added, None for the rest of the game.
+    pub fn for_clock(remaining: Duration, increment: Duration, moves_to_go: Option<u32>) -> Limit {
+        // expect 30 more moves if nobody says, and never use up the clock on a single move
+        let moves = moves_to_go.unwrap_or(30).max(1);
+        Limit::time((remaining / moves + increment * 3 / 4).min(remaining * 4 / 5))
+    }
+}
+
+impl Score {
+    /// Return the score for a value of the search.
+    fn from_value(value: i32) -> Score {
+        if value > MATE - MAX_PLY as i32 {
+            Score::Mate((MATE - value + 1) / 2)
+        }
+        else if value < -MATE + MAX_PLY as i32 {
+            Score::Mate(-(MATE + value) / 2)
+        }
+        else {
+            Score::Centipawns(value)
+        }
+    }
+}
+
+impl SearchResult {
+    /// The best move found.
+    pub fn mv(&self) -> Move {
+        self.mv
+    }
+
+    /// The score of the position for the side to move, expecting the best play by both sides.
+    pub fn score(&self) -> Score {
+        self.score
+    }
+
+    /// The principal variation, the moves expected to be played, starting with `mv`.
+    pub fn pv(&self) -> &[Move] {
+        &self.pv
+    }
+
+    /// The depth in half moves of the last finished iteration.
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// The number of positions looked at.
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+}
+
+impl Game {
+    /// Search for the best move of the side to move within `limit`,
+    /// None if the game is over. Nothing is kept for the next search,
+    /// so keep a `Searcher` to search again and again.
+    pub fn best_move(&self, limit: Limit) -> Option<SearchResult> {
+        let table = TranspositionTable::new(BEST_MOVE_MEGABYTES);
+        Searcher::with_table(DefaultEvaluator::new(), table).search(self, limit)
+    }
+}
+
+impl Default for Searcher {
+    fn default() -> Self {
+        Searcher::new()
+    }
+}
+
+impl Searcher {
+    /// A searcher with the default evaluation that knows nothing about the moves yet.
+    pub fn new() -> Searcher {
+        Searcher::with_evaluator(DefaultEvaluator::new())
+    }
+}
+
+impl<E: Evaluator> Searcher<E> {
+    /// A searcher with the given evaluation that knows nothing about the moves yet.
+    pub fn with_evaluator(evaluator: E) -> Searcher<E> {
+        Searcher::with_table(evaluator, TranspositionTable::new(transposition::DEFAULT_MEGABYTES))
+    }
+
+    /// A searcher with the given evaluation and transposition table.
+    fn with_table(evaluator: E, table: TranspositionTable) -> Searcher<E> {
+        Searcher {
+            evaluator,
+            killers: [[None; 2]; MAX_PLY],
+            history: [[0; 64]; 64],
+            table,
+            pv: vec!(MoveList::new(); MAX_PLY + 1),
+            previous_pv: MoveList::new(),
+            follow_pv: false,
+            keys: vec!(),
+            nodes: 0,
+            limit: Limit::default(),
+            start: Instant::now(),
+            stop: Arc::new(AtomicBool::new(false)),
+            can_stop: false,
+            stopped: false
+        }
+    }
+
+    /// Return a flag that stops the search as soon as it is set, e.g. from
+    /// another thread, once the first iteration has found a move. It stays
+    /// set, stopping every later search, until it is cleared again.
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.stop)
+    }
+
+    /// Use a transposition table of about the given number of megabytes,
+    /// forgetting what the current one holds. It takes 16 megabytes by default
+    /// and `MAX_HASH_MEGABYTES` for any larger size.
+    pub fn set_hash_size(&mut self, megabytes: usize) {
+        self.table = TranspositionTable::new(megabytes);
+    }
+
+    /// Forget the positions searched so far, e.g. before a new game.
+    pub fn clear_hash(&mut self) {
+        self.table.clear();
+    }
+
+    /// Use another evaluation, keeping what was learned about moves and positions.
+    pub fn set_evaluator(&mut self, evaluator: E) {
+        self.evaluator = evaluator;
+    }
+
+    /// Search for the best move of the side to move within `limit`,
+    /// None if the game is over.
+    pub fn search(&mut self, game: &Game, limit: Limit) -> Option<SearchResult> {
+        self.search_with(game, limit, |_| {})
+    }
+
+    /// Like `search`, but call `report` with the result of every finished iteration.
+    pub fn search_with(&mut self, game: &Game, limit: Limit, mut report: impl FnMut(&SearchResult)) -> Option<SearchResult> {
+        let root = game.position;
+        let moves = game.legal_moves();
+        let first = *moves.first()?;
+
+        self.keys = game.positions.iter().map(Position::hash).collect();
+        self.killers = [[None; 2]; MAX_PLY];
+        self.previous_pv.clear();
+        self.table.new_search();
+        self.nodes = 0;
+        self.limit = limit;
+        self.start = Instant::now();
+        self.can_stop = false;
+        self.stopped = false;
+
+        let mut result = SearchResult {mv: first, score: Score::Centipawns(0), pv: vec!(first), depth: 0, nodes: 0};
+        let max_depth = limit.depth.map_or(MAX_PLY as u32, |depth| depth.clamp(1, MAX_PLY as u32));
+        for depth in 1..=max_depth {
+            self.follow_pv = true;
+            let value = self.negamax(&root, depth, 0, -INFINITY, INFINITY);
+            // an unfinished iteration may have missed the best move
+            if self.stopped {
+                break;
+            }
+            self.can_stop = true;
+            self.previous_pv = self.pv[0];
+            result = SearchResult {
+                mv: self.pv[0].first().copied().unwrap_or(first),
+                score: Score::from_value(value),
+                pv: self.pv[0].to_vec(),
+                depth,
+                nodes: self.nodes
+            };
+            report(&result);
+            // searching deeper will not find a shorter mate
+            if value.abs() > MATE - depth as i32 {
+                break;
+            }
+        }
+        result.nodes = self.nodes;
+        Some(result)
+    }
+
+    /// Return the value of `position` for the side to move, searching
+    /// `depth` half moves deep, `ply` half moves below the root. Values
+    /// at most `alpha` or at least `beta` are only bounds.
+    fn negamax(&mut self, position: &Position, depth: u32, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.pv[ply].clear();
+        if ply > 0 && self.is_draw(position) {
+            return 0;
+        }
+        if depth == 0 || ply >= MAX_PLY {
+            return self.quiescence(position, ply, alpha, beta);
+        }
+        self.count_node();
+
+        let key = *self.keys.last().expect("the current position is recorded");
+        let entry = self.table.probe(key);
+        if let Some(entry) = entry.filter(|entry| ply > 0 && entry.depth >= depth) {
+            let value = value_from_table(entry.value, ply);
+            // an exact value within the window would cut the principal variation short
+            let cutoff = match entry.bound {
+                Bound::Exact => value <= alpha || value >= beta,
+                Bound::Lower => value >= beta,
+                Bound::Upper => value <= alpha
+            };
+            if cutoff {
+                return value;
+            }
+        }
+
+        let mut moves = position.legal_moves();
+        if moves.is_empty() {
+            return if position.in_check(position.active) {-MATE + ply as i32} else {0};
+        }
+        let pv_move = if self.follow_pv {self.previous_pv.get(ply).copied()} else {None};
+        let table_move = entry.and_then(|entry| entry.best_move);
+        self.order(position, &mut moves, ply, pv_move.or(table_move));
+
+        let original_alpha = alpha;
+        let mut best = -INFINITY;
+        let mut best_move = None;
+        for (i, &mv) in moves.iter().enumerate() {
+            if i > 0 {
+                self.follow_pv = false;
+            }
+            else {
+                self.follow_pv = self.follow_pv && Some(mv) == pv_move;
+            }
+            let after = play(position, mv);
+            self.keys.push(after.hash());
+            let value = -self.negamax(&after, depth - 1, ply + 1, -beta, -alpha);
+            self.keys.pop();
+            if self.stopped {
+                return 0;
+            }
+
+            if value > best {
+                best = value;
+                best_move = Some(mv);
+            }
+            if value > alpha {
+                alpha = value;
+                self.update_pv(ply, mv);
+            }
+            if value >= beta {
+                if !position.is_capture(&mv) && mv.promotion().is_none() {
+                    self.reward_quiet(mv, depth, ply);
+                }
+                break;
+            }
+        }
+
+        let bound = if best >= beta {
+            Bound::Lower
+        }
+        else if best > original_alpha {
+            Bound::Exact
+        }
+        else {
+            Bound::Upper
+        };
+        // the best of the moves that all failed low is no better than the others
+        let best_move = if bound == Bound::Upper {None} else {best_move};
+        self.table.store(key, depth, bound, value_to_table(best, ply), best_move);
+        best
+    }
+
+    /// Search captures and queen promotions only until the position is
+    /// quiet, so that the evaluation does not miss pieces hanging. The side
+    /// to move may also stand pat and keep the static value, unless it is
+    /// in check, when all moves are searched.
+    fn quiescence(&mut self, position: &Position, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.pv[ply].clear();
+        self.count_node();
+
+        let in_check = position.in_check(position.active);
+        let mut moves = position.legal_moves();
+        if moves.is_empty() {
+            return if in_check {-MATE + ply as i32} else {0};
+        }
+        if ply >= MAX_PLY {
+            return self.evaluator.evaluate(position);
+        }
+
+        let mut best = -INFINITY;
+        if !in_check {
+            best = self.evaluator.evaluate(position);
+            if best >= beta {
+                return best;
+            }
+            alpha = alpha.max(best);
+            moves.retain(|mv| position.is_capture(mv) || mv.promotion() == Some(Promotion::Queen));
+        }
+        self.order(position, &mut moves, ply, None);
+
+        for &mv in moves.iter() {
+            let value = -self.quiescence(&play(position, mv), ply + 1, -beta, -alpha);
+            if self.stopped {
+                return 0;
+            }
+            if value > best {
+                best = value;
+            }
+            if value >= beta {
+                break;
+            }
+            alpha = alpha.max(value);
+        }
+        best
+    }
+
+    /// Return true if the position is drawn by the fifty-move rule, a lack
+    /// of material or a repetition, where one repetition is enough as the
+    /// side to move could just as well repeat again.
+    fn is_draw(&self, position: &Position) -> bool {
+        let key = *self.keys.last().expect("the current position is recorded");
+        position.halfmove_clock >= 100
+            || position.insufficient_material()
+            || self.keys.iter().rev()
+                .take(position.halfmove_clock as usize + 1)
+                .skip(2)
+                .step_by(2)
+                .any(|&other| other == key)
+    }
+
+    /// Count a node and stop the search if a limit is reached, but
+    /// not before the first iteration has found a move.
+    fn count_node(&mut self) {
+        self.nodes += 1;
+        if !self.can_stop {
+            return;
+        }
+        if self.limit.nodes.is_some_and(|nodes| self.nodes >= nodes) {
+            self.stopped = true;
+        }
+        if self.nodes.is_multiple_of(CLOCK_INTERVAL) && self.limit.time.is_some_and(|time| self.start.elapsed() >= time) {
+            self.stopped = true;
+        }
+        if self.stop.load(Ordering::Relaxed) {
+            self.stopped = true;
+        }
+    }
+
+    /// Sort the moves so that those most likely to be best come first: the
+    /// move of the last principal variation or the transposition table, captures of valuable pieces by
+    /// cheap ones, killer moves, then quiet moves by their history.
+    fn order(&self, position: &Position, moves: &mut MoveList, ply: usize, best_move: Option<Move>) {
+        let killers = self.killers.get(ply).copied().unwrap_or([None; 2]);
+        moves.sort_unstable_by_key(|&mv| Reverse(if Some(mv) == best_move {
+            1_000_000
+        }
+        else if position.is_capture(&mv) {
+            // an en passant capture takes a pawn from an empty square
+            let victim = position.get_piece(mv.to()).unwrap_or(Piece::Pawn(Color::White)).value();
+            let attacker = position.get_piece(mv.from()).map_or(0, |piece| piece.value());
+            500_000 + 10 * victim - attacker / 10 + promotion_value(mv)
+        }
+        else if mv.promotion().is_some() {
+            400_000 + promotion_value(mv)
+        }
+        else if Some(mv) == killers[0] {
+            300_000
+        }
+        else if Some(mv) == killers[1] {
+            200_000
+        }
+        else {
+            self.history[mv.from().index()][mv.to().index()]
+        }));
+    }
+
+    /// Remember a quiet move that caused a cutoff as a killer and in the history.
+    fn reward_quiet(&mut self, mv: Move, depth: u32, ply: usize) {
+        if self.killers[ply][0] != Some(mv) {
+            self.killers[ply][1] = self.killers[ply][0];
+            self.killers[ply][0] = Some(mv);
+        }
+        let entry = &mut self.history[mv.from().index()][mv.to().index()];
+        *entry += (depth * depth) as i32;
+        // keep history below the killers, the older the entries the less they count
+        if *entry >= 100_000 {
+            for value in self.history.iter_mut().flatten() {
+                *value /= 2;
+            }
+        }
+    }
+
+    /// Make `mv` followed by the best line after it the best line at `ply`.
+    fn update_pv(&mut self, ply: usize, mv: Move) {
+        let (line, deeper) = self.pv.split_at_mut(ply + 1);
+        let line = &mut line[ply];
+        line.clear();
+        line.push(mv);
+        line.extend(deeper[0].iter().copied());
+    }
+}
+
+/// Return the position after the legal move `mv`.
+fn play(position: &Position, mv: Move) -> Position {
+    let mut after = *position;
+    after.apply_move(mv.from(), mv.to(), mv.promotion().unwrap_or(Promotion::Queen));
+    after
+}
+
+/// Return the value to store in the transposition table for a value found
+/// `ply` half moves below the root. Mates are counted from the position
+/// rather than from the root, as the position may be reached at other plies.
+fn value_to_table(value: i32, ply: usize) -> i32 {
+    if value > MATE - MAX_PLY as i32 {
+        value + ply as i32
+    }
+    else if value < -MATE + MAX_PLY as i32 {
+        value - ply as i32
+    }
+    else {
+        value
+    }
+}
+
+/// Return the value for `ply` half moves below the root of a value stored
+/// in the transposition table, undoing `value_to_table`.
+fn value_from_table(value: i32, ply: usize) -> i32 {
+    if value > MATE - MAX_PLY as i32 {
+        value - ply as i32
+    }
+    else if value < -MATE + MAX_PLY as i32 {
+        value + ply as i32
+    }
+    else {
+        value
+    }
+}
+
+/// Return what a promotion adds in centipawns, 0 for other moves.
+fn promotion_value(mv: Move) -> i32 {
+    mv.promotion().map_or(0, |promotion| promotion.to_piece(Color::White).value() - Piece::Pawn(Color::White).value())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
+
+    use super::{Limit, Score, Searcher, MATE, value_from_table, value_to_table};
+    use crate::{Color, Evaluator, Game, Piece, Position, Termination};
+
+    fn best(fen: &str, depth: u32) -> (String, Score) {
+        let result = Game::from_fen(fen).unwrap().best_move(Limit::depth(depth)).unwrap();
+        (result.mv().to_string(), result.score())
+    }
+
+    #[test]
+    fn finds_mate_in_one() {
+        assert_eq!(best("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 2), ("a1a8".to_string(), Score::Mate(1)));
+    }
+
+    #[test]
+    fn finds_mate_in_two() {
+        let mut game = Game::from_fen("7k/8/8/8/8/8/R7/1R4K1 w - - 0 1").unwrap();
+        let result = game.best_move(Limit::depth(4)).unwrap();
+        assert_eq!(result.score(), Score::Mate(2));
+        for &mv in result.pv() {
+            game.play(mv).unwrap();
+        }
+        assert_eq!(game.termination(), Some(Termination::Checkmate));
+    }
+
+    #[test]
+    fn sees_being_mated() {
+        // whichever way the king goes, Ra1 is mate
+        let result = Game::from_fen("r6k/8/8/8/8/5p1p/5P1P/6K1 w - - 0 1").unwrap().best_move(Limit::depth(3)).unwrap();
+        assert_eq!(result.score(), Score::Mate(-1));
+    }
+
+    #[test]
+    fn takes_hanging_pieces_but_not_defended_ones() {
+        assert_eq!(best("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", 2).0, "d2d5");
+        // the rook is defended by a pawn, taking it with the queen loses material
+        let (mv, _) = best("4k3/8/2p5/3r4/8/8/3Q4/4K3 w - - 0 1", 3);
+        assert_ne!(mv, "d2d5");
+    }
+
+    #[test]
+    fn principal_variation_is_legal() {
+        let game = Game::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let result = game.best_move(Limit::depth(3)).unwrap();
+        assert_eq!(result.depth(), 3);
+        assert_eq!(result.pv()[0], result.mv());
+        let mut line = game.clone();
+        for &mv in result.pv() {
+            line.play(mv).unwrap();
+        }
+    }
+
+    #[test]
+    fn limits_stop_the_search() {
+        let game = Game::new();
+        let result = game.best_move(Limit::nodes(500)).unwrap();
+        assert!(result.nodes() < 1000);
+        assert!(game.legal_moves().contains(&result.mv()));
+
+        let result = Searcher::new().search(&game, Limit::time(Duration::from_millis(50))).unwrap();
+        assert!(result.depth() >= 1);
+    }
+
+    #[test]
+    fn searches_report_and_stop_when_told() {
+        let game = Game::new();
+        let mut searcher = Searcher::new();
+        let mut depths = vec!();
+        let result = searcher.search_with(&game, Limit::depth(3), |result| depths.push(result.depth())).unwrap();
+        assert_eq!(depths, [1, 2, 3]);
+        assert_eq!(result.depth(), 3);
+
+        // without limits only the flag stops the search, right after the first iteration
+        searcher.stop_flag().store(true, Ordering::Relaxed);
+        assert_eq!(searcher.search(&game, Limit::default()).unwrap().depth(), 1);
+    }
+
+    #[test]
+    fn clocks_are_shared_out_over_the_moves() {
+        let minute = Duration::from_secs(60);
+        assert_eq!(Limit::for_clock(minute, Duration::ZERO, None), Limit::time(Duration::from_secs(2)));
+        assert_eq!(Limit::for_clock(minute, Duration::from_secs(4), Some(10)), Limit::time(Duration::from_secs(9)));
+        assert_eq!(Limit::for_clock(minute, Duration::ZERO, Some(1)), Limit::time(Duration::from_secs(48)));
+    }
+
+    #[test]
+    fn evaluators_can_be_replaced() {
+        /// Likes nothing better than having the white king on h1.
+        struct KingToCorner;
+
+        impl Evaluator for KingToCorner {
+            fn evaluate(&self, position: &Position) -> i32 {
+                let white = if position.piece_at("h1".parse().unwrap()) == Some(Piece::King(Color::White)) {100} else {0};
+                if position.active_color() == Color::White {white} else {-white}
+            }
+        }
+
+        let game = Game::from_fen("4k3/p7/8/8/8/8/P7/6K1 w - - 0 1").unwrap();
+        let result = Searcher::with_evaluator(KingToCorner).search(&game, Limit::depth(2)).unwrap();
+        assert_eq!(result.mv().to_string(), "g1h1");
+        assert_eq!(result.score(), Score::Centipawns(100));
+    }
+
+    #[test]
+    fn the_transposition_table_saves_work() {
+        let game = Game::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3").unwrap();
+        let mut searcher = Searcher::new();
+        let first = searcher.search(&game, Limit::depth(3)).unwrap();
+        let again = searcher.search(&game, Limit::depth(3)).unwrap();
+        assert!(again.nodes() < first.nodes() / 2, "{} after {}", again.nodes(), first.nodes());
+        assert_eq!((again.mv(), again.score()), (first.mv(), first.score()));
+
+        searcher.clear_hash();
+        assert!(searcher.search(&game, Limit::depth(3)).unwrap().nodes() > again.nodes() * 2);
+        // a tiny table keeps working, just with less help
+        searcher.set_hash_size(0);
+        assert_eq!(searcher.search(&game, Limit::depth(3)).unwrap().score(), first.score());
+    }
+
+    #[test]
+    fn mates_are_stored_relative_to_the_position() {
+        // mate in 3 half moves from a position 5 half moves below the root
+        let value = MATE - 8;
+        assert_eq!(value_to_table(value, 5), MATE - 3);
+        assert_eq!(value_from_table(MATE - 3, 2), MATE - 5);
+        assert_eq!(value_from_table(value_to_table(-value, 5), 5), -value);
+        assert_eq!(value_to_table(150, 5), 150);
+
+        // mates found through the table still lead to mate
+        let mut game = Game::from_fen("7k/8/8/8/8/8/R7/1R4K1 w - - 0 1").unwrap();
+        let mut searcher = Searcher::new();
+        searcher.search(&game, Limit::depth(4)).unwrap();
+        let result = searcher.search(&game, Limit::depth(4)).unwrap();
+        assert_eq!(result.score(), Score::Mate(2));
+        for &mv in result.pv() {
+            game.play(mv).unwrap();
+        }
+        assert_eq!(game.termination(), Some(Termination::Checkmate));
+    }
+
+    #[test]
+    fn finished_games_have_no_best_move() {
+        assert_eq!(Game::from_fen("7k/6Q1/6K1/8/8/8/8/8 b - - 0 1").unwrap().best_move(Limit::depth(1)), None);
+    }
+}
@@ -0,0 +1,237 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// A column of the board, from A on White's left to H on White's right.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum File {
+    A, B, C, D, E, F, G, H
+}
+
+/// A row of the board, from the first rank on White's side to the eighth on Black's.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Rank {
+    First, Second, Third, Fourth, Fifth, Sixth, Seventh, Eighth
+}
+
+/// A tile of the board, e.g. e4.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Square {
+    /// a1 = 0, b1 = 1, ..., h1 = 7, a2 = 8, ..., h8 = 63
+    index: u8
+}
+
+/// Error returned when a file, rank or square cannot be read from a string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseSquareError;
+
+impl File {
+    /// All files from A to H.
+    pub const ALL: [File; 8] = [File::A, File::B, File::C, File::D, File::E, File::F, File::G, File::H];
+
+    /// Return the file with the given index, 0 for A up to 7 for H.
+    pub fn from_index(index: usize) -> Option<File> {
+        File::ALL.get(index).copied()
+    }
+
+    /// Return the index of the file, 0 for A up to 7 for H.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Read a file from its letter, in upper or lower case.
+    pub fn from_char(c: char) -> Option<File> {
+        match c.to_ascii_lowercase() {
+            c @ 'a'..='h' => File::from_index(c as usize - 'a' as usize),
+            _ => None
+        }
+    }
+
+    /// Return the lower case letter of the file.
+    pub fn to_char(self) -> char {
+        (b'a' + self as u8) as char
+    }
+}
+
+impl Rank {
+    /// All ranks from the first to the eighth.
+    pub const ALL: [Rank; 8] = [
+        Rank::First, Rank::Second, Rank::Third, Rank::Fourth,
+        Rank::Fifth, Rank::Sixth, Rank::Seventh, Rank::Eighth
+    ];
+
+    /// Return the rank with the given index, 0 for the first up to 7 for the eighth.
+    pub fn from_index(index: usize) -> Option<Rank> {
+        Rank::ALL.get(index).copied()
+    }
+
+    /// Return the index of the rank, 0 for the first up to 7 for the eighth.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Read a rank from its digit, '1' to '8'.
+    pub fn from_char(c: char) -> Option<Rank> {
+        match c {
+            '1'..='8' => Rank::from_index(c as usize - '1' as usize),
+            _ => None
+        }
+    }
+
+    /// Return the digit of the rank.
+    pub fn to_char(self) -> char {
+        (b'1' + self as u8) as char
+    }
+}
+
+impl Square {
+    /// Return the square on the given file and rank.
+    pub fn new(file: File, rank: Rank) -> Square {
+        Square {index: (rank.index() * 8 + file.index()) as u8}
+    }
+
+    /// Return the square with the given index, a1 = 0, b1 = 1, ..., h8 = 63.
+    pub fn from_index(index: usize) -> Option<Square> {
+        if index < 64 {Some(Square {index: index as u8})} else {None}
+    }
+
+    /// Return the index of the square, a1 = 0, b1 = 1, ..., h8 = 63.
+    pub fn index(self) -> usize {
+        self.index as usize
+    }
+
+    pub fn file(self) -> File {
+        File::ALL[self.index() % 8]
+    }
+
+    pub fn rank(self) -> Rank {
+        Rank::ALL[self.index() / 8]
+    }
+
+    /// Return the square `files` to the right and `ranks` up from White's view,
+    /// if it is on the board.
+    pub fn offset(self, files: i8, ranks: i8) -> Option<Square> {
+        let file = self.file().index() as i8 + files;
+        let rank = self.rank().index() as i8 + ranks;
+        if (0..8).contains(&file) && (0..8).contains(&rank) {
+            Some(Square::new(File::ALL[file as usize], Rank::ALL[rank as usize]))
+        }
+        else {
+            None
+        }
+    }
+
+    /// Iterate over all squares, a1, b1, ..., h1, a2, ..., h8.
+    pub fn all() -> impl Iterator<Item = Square> {
+        (0..64).map(|index| Square {index})
+    }
+}
+
+impl fmt::Display for File {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
+}
+
+impl fmt::Display for Rank {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.file(), self.rank())
+    }
+}
+
+impl fmt::Debug for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl fmt::Display for ParseSquareError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "not a square on the board")
+    }
+}
+
+impl std::error::Error for ParseSquareError {}
+
+impl FromStr for File {
+    type Err = ParseSquareError;
+
+    fn from_str(s: &str) -> Result<File, ParseSquareError> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => File::from_char(c).ok_or(ParseSquareError),
+            _ => Err(ParseSquareError)
+        }
+    }
+}
+
+impl FromStr for Rank {
+    type Err = ParseSquareError;
+
+    fn from_str(s: &str) -> Result<Rank, ParseSquareError> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Rank::from_char(c).ok_or(ParseSquareError),
+            _ => Err(ParseSquareError)
+        }
+    }
+}
+
+impl FromStr for Square {
+    type Err = ParseSquareError;
+
+    /// Read a square such as "e4" or "E4".
+    fn from_str(s: &str) -> Result<Square, ParseSquareError> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some(file), Some(rank), None) => {
+                let file = File::from_char(file).ok_or(ParseSquareError)?;
+                let rank = Rank::from_char(rank).ok_or(ParseSquareError)?;
+                Ok(Square::new(file, rank))
+            },
+            _ => Err(ParseSquareError)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{File, Rank, Square, ParseSquareError};
+
+    #[test]
+    fn squares_are_read_and_written() {
+        let square: Square = "E4".parse().unwrap();
+        assert_eq!(square, Square::new(File::E, Rank::Fourth));
+        assert_eq!(square.to_string(), "e4");
+        assert_eq!("h8".parse::<Square>().unwrap().index(), 63);
+    }
+
+    #[test]
+    fn invalid_squares_are_rejected() {
+        for s in ["", "e", "i4", "e9", "e0", "e44", "4e"] {
+            assert_eq!(s.parse::<Square>(), Err(ParseSquareError), "{}", s);
+        }
+    }
+
+    #[test]
+    fn all_squares_are_visited_in_order() {
+        let squares: Vec<Square> = Square::all().collect();
+        assert_eq!(squares.len(), 64);
+        assert_eq!(squares[0].to_string(), "a1");
+        assert_eq!(squares[8].to_string(), "a2");
+        assert!(squares.iter().enumerate().all(|(index, square)| square.index() == index));
+    }
+
+    #[test]
+    fn offset_stays_on_board() {
+        let square = Square::new(File::A, Rank::First);
+        assert_eq!(square.offset(1, 2), Some(Square::new(File::B, Rank::Third)));
+        assert_eq!(square.offset(-1, 0), None);
+        assert_eq!(square.offset(0, 8), None);
+    }
+}
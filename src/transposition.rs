@@ -0,0 +1,149 @@
+use crate::Move;
+
+/// Whether the value of an entry is exact or only a bound, because the
+/// search of the position was cut short by the alpha-beta window.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Bound {
+    Exact,
+    /// The value is at least the stored one, a move was found that was too good.
+    Lower,
+    /// The value is at most the stored one, no move reached alpha.
+    Upper
+}
+
+/// What the search found out about a position.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Entry {
+    /// The key of the position, as positions with different keys share slots.
+    key: u64,
+    pub(crate) best_move: Option<Move>,
+    /// The value, with mates counted from the position, see `search::value_to_table`.
+    pub(crate) value: i32,
+    pub(crate) depth: u32,
+    pub(crate) bound: Bound,
+    /// The search the entry was stored in, see `TranspositionTable::new_search`.
+    generation: u8
+}
+
+/// Remembers the results of searching positions by their Zobrist key, so
+/// that positions reached again, by transposition or in the next iteration,
+/// need not be searched again. It has a fixed number of slots, in which an
+/// entry replaces an older one unless that is from the same search and deeper.
+#[derive(Clone, Debug)]
+pub(crate) struct TranspositionTable {
+    entries: Vec<Option<Entry>>,
+    generation: u8
+}
+
+/// The size of a table unless another one is chosen.
+pub(crate) const DEFAULT_MEGABYTES: usize = 16;
+
+/// The largest size of a table, larger sizes are taken as this one.
+pub(crate) const MAX_MEGABYTES: usize = 1024;
+
+impl TranspositionTable {
+    /// An empty table taking up about the given number of megabytes, at
+    /// least one slot and at most `MAX_MEGABYTES`.
+    pub(crate) fn new(megabytes: usize) -> TranspositionTable {
+        TranspositionTable {entries: vec!(None; slots(megabytes)), generation: 0}
+    }
+
+    /// Forget all entries.
+    pub(crate) fn clear(&mut self) {
+        self.entries.iter_mut().for_each(|entry| *entry = None);
+        self.generation = 0;
+    }
+
+    /// Start a new search, which makes the entries stored so far easier to replace.
+    pub(crate) fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    /// Return the entry for the position with the given key, if there is one.
+    pub(crate) fn probe(&self, key: u64) -> Option<Entry> {
+        self.entries[self.index(key)].filter(|entry| entry.key == key)
+    }
+
+    /// Store what the search found for the position with the given key.
+    pub(crate) fn store(&mut self, key: u64, depth: u32, bound: Bound, value: i32, best_move: Option<Move>) {
+        let generation = self.generation;
+        let index = self.index(key);
+        let slot = &mut self.entries[index];
+        let best_move = match slot {
+            // deeper results of the current search are worth more than others
+            Some(old) if old.key != key && old.generation == generation && old.depth > depth => return,
+            // a search that found no move keeps the move found before
+            Some(old) if old.key == key => best_move.or(old.best_move),
+            _ => best_move
+        };
+        *slot = Some(Entry {key, best_move, value, depth, bound, generation});
+    }
+
+    /// Return the slot for the key, spreading the keys evenly over the slots.
+    fn index(&self, key: u64) -> usize {
+        ((key as u128 * self.entries.len() as u128) >> 64) as usize
+    }
+}
+
+/// Return the number of slots of a table of the given number of megabytes.
+fn slots(megabytes: usize) -> usize {
+    let bytes = megabytes.min(MAX_MEGABYTES) << 20;
+    (bytes / std::mem::size_of::<Option<Entry>>()).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bound, TranspositionTable, MAX_MEGABYTES, slots};
+    use crate::Move;
+
+    fn mv(uci: &str) -> Option<Move> {
+        Some(uci.parse().unwrap())
+    }
+
+    #[test]
+    fn entries_are_found_by_key() {
+        let mut table = TranspositionTable::new(1);
+        assert!(table.entries.len() > 1000);
+        table.store(42, 3, Bound::Exact, 25, mv("e2e4"));
+        let entry = table.probe(42).unwrap();
+        assert_eq!((entry.depth, entry.bound, entry.value, entry.best_move), (3, Bound::Exact, 25, mv("e2e4")));
+        assert_eq!(table.probe(43), None);
+
+        table.clear();
+        assert_eq!(table.probe(42), None);
+    }
+
+    #[test]
+    fn deeper_entries_of_the_same_search_stay() {
+        // with a single slot every key competes for it
+        let mut table = TranspositionTable::new(0);
+        assert_eq!(table.entries.len(), 1);
+        table.store(1, 5, Bound::Lower, 10, mv("e2e4"));
+        table.store(2, 2, Bound::Exact, 20, mv("d2d4"));
+        assert_eq!(table.probe(1).unwrap().depth, 5);
+        assert_eq!(table.probe(2), None);
+
+        // but not into the next search
+        table.new_search();
+        table.store(2, 2, Bound::Exact, 20, mv("d2d4"));
+        assert_eq!(table.probe(1), None);
+        assert_eq!(table.probe(2).unwrap().value, 20);
+    }
+
+    #[test]
+    fn sizes_are_limited() {
+        assert_eq!(slots(0), 1);
+        assert!(slots(MAX_MEGABYTES) > slots(MAX_MEGABYTES / 2));
+        assert_eq!(slots(MAX_MEGABYTES + 1), slots(MAX_MEGABYTES));
+        assert_eq!(slots(usize::MAX), slots(MAX_MEGABYTES));
+    }
+
+    #[test]
+    fn the_same_position_keeps_its_move() {
+        let mut table = TranspositionTable::new(1);
+        table.store(7, 4, Bound::Lower, 10, mv("g1f3"));
+        table.store(7, 2, Bound::Upper, -5, None);
+        let entry = table.probe(7).unwrap();
+        assert_eq!((entry.depth, entry.bound, entry.best_move), (2, Bound::Upper, mv("g1f3")));
+    }
+}
@@ -0,0 +1,140 @@
+use std::fmt;
+
+use crate::{File, Game, Move, Piece, Square};
+use crate::position::Position;
+
+/// Why a move in UCI long algebraic notation could not be read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UciError {
+    /// The string is not written as two squares and an optional
+    /// promotion letter, e.g. "e2e" or "e7e8k".
+    InvalidSyntax,
+    /// The move is not legal in the current position.
+    IllegalMove,
+    /// The move promotes a pawn, but no promotion letter was given.
+    PromotionRequired
+}
+
+impl fmt::Display for UciError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            UciError::InvalidSyntax => "not a move such as e2e4 or e7e8q",
+            UciError::IllegalMove => "the move is not legal",
+            UciError::PromotionRequired => "the move needs a promotion letter"
+        };
+        write!(f, "{}", message)
+    }
+}
+
+impl std::error::Error for UciError {}
+
+impl Game {
+    /// Read a move as sent by UCI engines and GUIs, e.g. "e2e4" or "e7e8q",
+    /// and return the legal move it stands for in the current position.
+    /// Castling is accepted both as the king moving two squares, "e1g1",
+    /// and as the king taking its own rook as in Chess960, "e1h1".
+    pub fn parse_uci(&self, uci: &str) -> Result<Move, UciError> {
+        parse_uci(&self.position, uci)
+    }
+
+    /// Write a move as expected by UCI engines and GUIs, e.g. "e7e8q".
+    /// Castling is written as the king moving two squares, "e1g1".
+    pub fn to_uci(&self, mv: &Move) -> String {
+        mv.to_string()
+    }
+
+    /// Like `to_uci`, but castling is written as the king taking its own
+    /// rook, "e1h1", as UCI does in Chess960 mode.
+    pub fn to_uci_chess960(&self, mv: &Move) -> String {
+        to_uci_chess960(&self.position, mv)
+    }
+}
+
+pub(crate) fn parse_uci(position: &Position, uci: &str) -> Result<Move, UciError> {
+    let mv: Move = uci.parse().map_err(|_| UciError::InvalidSyntax)?;
+    let color = position.active;
+
+    // in Chess960 notation the king moves onto its own rook
+    let castling_rook = position.get_piece(mv.from()) == Some(Piece::King(color))
+        && position.get_piece(mv.to()) == Some(Piece::Rook(color))
+        && mv.from().rank() == mv.to().rank();
+    let mv = if castling_rook {
+        let file = if mv.to().file() > mv.from().file() {File::G} else {File::C};
+        Move::new(mv.from(), Square::new(file, mv.from().rank()))
+    }
+    else {
+        mv
+    };
+
+    let legal_moves: Vec<Move> = position.legal_moves().into_iter()
+        .filter(|legal| legal.from() == mv.from())
+        .collect();
+    if legal_moves.contains(&mv) {
+        Ok(mv)
+    }
+    else if mv.promotion().is_none() && legal_moves.iter().any(|legal| legal.to() == mv.to()) {
+        Err(UciError::PromotionRequired)
+    }
+    else {
+        Err(UciError::IllegalMove)
+    }
+}
+
+pub(crate) fn to_uci_chess960(position: &Position, mv: &Move) -> String {
+    let castling = matches!(position.get_piece(mv.from()), Some(Piece::King(_)))
+        && mv.from().file() == File::E && mv.from().rank() == mv.to().rank()
+        && (mv.to().file() == File::G || mv.to().file() == File::C);
+    if castling {
+        let file = if mv.to().file() == File::G {File::H} else {File::A};
+        format!("{}{}", mv.from(), Square::new(file, mv.from().rank()))
+    }
+    else {
+        mv.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UciError;
+    use crate::{Game, Move};
+
+    fn mv(s: &str) -> Move {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn uci_is_parsed() {
+        let game = Game::new();
+        assert_eq!(game.parse_uci("e2e4"), Ok(mv("e2e4")));
+        assert_eq!(game.parse_uci("g1f3"), Ok(mv("g1f3")));
+        assert_eq!(game.parse_uci("e2e5"), Err(UciError::IllegalMove));
+        assert_eq!(game.parse_uci("e7e5"), Err(UciError::IllegalMove));
+        assert_eq!(game.parse_uci("e2e4q"), Err(UciError::IllegalMove));
+        for s in ["", "e2", "e2e", "e2e9", "e2e4k", "e2e4qq"] {
+            assert_eq!(game.parse_uci(s), Err(UciError::InvalidSyntax), "{}", s);
+        }
+    }
+
+    #[test]
+    fn promotions_need_a_letter() {
+        let game = Game::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(game.parse_uci("b7b8n"), Ok(mv("b7b8n")));
+        assert_eq!(game.parse_uci("b7b8"), Err(UciError::PromotionRequired));
+        assert_eq!(game.to_uci(&mv("b7b8r")), "b7b8r");
+    }
+
+    #[test]
+    fn both_castling_encodings_are_understood() {
+        let game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1").unwrap();
+        assert_eq!(game.parse_uci("e8g8"), Ok(mv("e8g8")));
+        assert_eq!(game.parse_uci("e8h8"), Ok(mv("e8g8")));
+        assert_eq!(game.parse_uci("e8a8"), Ok(mv("e8c8")));
+        assert_eq!(game.to_uci(&mv("e8c8")), "e8c8");
+        assert_eq!(game.to_uci_chess960(&mv("e8c8")), "e8a8");
+        assert_eq!(game.to_uci_chess960(&mv("e8g8")), "e8h8");
+        assert_eq!(game.to_uci_chess960(&mv("a8a1")), "a8a1");
+
+        let no_rights = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w - - 0 1").unwrap();
+        assert_eq!(no_rights.parse_uci("e1h1"), Err(UciError::IllegalMove));
+    }
+}